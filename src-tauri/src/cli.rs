@@ -0,0 +1,355 @@
+//! 命令行控制正在运行的实例，例如 `restloop pause 30m`、`restloop status --json`。
+//!
+//! 除 `status` 外的命令由 single-instance 插件转发给正在运行的实例执行；
+//! `status` 直接读取运行中的实例定期写入的状态快照文件，不需要启动界面。
+//! 没有正在运行的实例，或者指令在当前状态下不会生效时，以非零退出码结束，不会启动界面。
+
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  sync::mpsc::RecvTimeoutError,
+  time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{App, Manager};
+
+use crate::{
+  history::now_ms,
+  message::{Command, CounterBus, CounterBusState, CounterStatus},
+  timer::Phase,
+};
+
+const STATUS_FILE_NAME: &'static str = "status.json";
+/// 状态快照的写入间隔
+const STATUS_FILE_REFRESH: Duration = Duration::from_secs(5);
+/// 快照超过这么久没有更新，认为没有正在运行的实例
+const STATUS_FILE_STALE_MS: u64 = 15 * 1000;
+
+const USAGE: &'static str = "用法：
+  restloop pause [时长]   暂停，时长如 30m、1h、90s，不指定时无限期暂停
+  restloop pause tomorrow 暂停到下一个工作日
+  restloop resume         继续
+  restloop reset          重新开始本轮工作
+  restloop break-now      立即开始休息
+  restloop status [--json] 输出当前阶段和剩余秒数";
+
+/// 命令行指令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+  Pause(Option<u32>),
+  PauseUntilNextWorkday,
+  Resume,
+  Reset,
+  BreakNow,
+  Help,
+  Status { json: bool },
+}
+
+impl CliCommand {
+  /// 需要转发给计时器的指令
+  pub fn command(&self) -> Option<Command> {
+    match self {
+      CliCommand::Pause(secs) => Some(Command::Pause(*secs)),
+      CliCommand::PauseUntilNextWorkday => Some(Command::PauseUntilNextWorkday),
+      CliCommand::Resume => Some(Command::Resume),
+      CliCommand::Reset => Some(Command::Reset),
+      CliCommand::BreakNow => Some(Command::BreakNow),
+      CliCommand::Help | CliCommand::Status { .. } => None,
+    }
+  }
+
+  /// 指令在计时器当前状态下不会生效的原因，和托盘菜单的可用条件一致。
+  /// 命令行据此报错，正在运行的实例据此忽略指令。
+  pub fn rejection(&self, status: &CounterStatus) -> Option<&'static str> {
+    self.command()?.rejection(status)
+  }
+}
+
+/// 解析时长，支持 `30m`、`1h`、`90s`，不带单位时按分钟计算
+fn parse_duration(value: &str) -> Result<u32, String> {
+  let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+    Some(i) => value.split_at(i),
+    None => (value, "m"),
+  };
+  let scale = match unit {
+    "s" => 1,
+    "m" => 60,
+    "h" => 3600,
+    _ => return Err(format!("无法识别的时长：{}", value)),
+  };
+  number
+    .parse::<u32>()
+    .ok()
+    .and_then(|n| n.checked_mul(scale))
+    .filter(|&secs| secs > 0)
+    .ok_or_else(|| format!("无法识别的时长：{}", value))
+}
+
+/// 解析命令行参数，第一个参数是程序路径。不是命令行指令时返回 None，按正常方式启动。
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<CliCommand>, String> {
+  let args: Vec<String> = args.into_iter().skip(1).collect();
+  let Some(name) = args.first() else {
+    return Ok(None);
+  };
+  let rest = &args[1..];
+  let command = match (name.as_str(), rest) {
+    ("pause", []) => CliCommand::Pause(None),
+    ("pause", [value]) if value == "tomorrow" => CliCommand::PauseUntilNextWorkday,
+    ("pause", [value]) => CliCommand::Pause(Some(parse_duration(value)?)),
+    ("resume", []) => CliCommand::Resume,
+    ("reset", []) => CliCommand::Reset,
+    ("break-now", []) => CliCommand::BreakNow,
+    ("status", []) => CliCommand::Status { json: false },
+    ("status", [flag]) if flag == "--json" => CliCommand::Status { json: true },
+    ("help" | "--help" | "-h", _) => CliCommand::Help,
+    ("pause" | "resume" | "reset" | "break-now" | "status", _) => {
+      return Err(format!("参数错误：{}", args.join(" ")));
+    }
+    // 系统或自启动可能传入其它参数，当作正常启动
+    _ => return Ok(None),
+  };
+  Ok(Some(command))
+}
+
+/// 计时器状态的快照文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusSnapshot {
+  #[serde(flatten)]
+  pub status: CounterStatus,
+  /// 写入快照时的 unix 毫秒时间戳
+  pub updated_at: u64,
+}
+
+/// 状态快照文件的路径，写入和读取都通过这里获取。
+///
+/// 命令行读取时界面还没有启动，不能通过 tauri 获取应用数据目录，
+/// 这里和 tauri 的 `app_data_dir` 一样使用 `dirs::data_dir` 加上应用标识。
+fn status_file_path(identifier: &str) -> Option<PathBuf> {
+  dirs::data_dir().map(|dir| dir.join(identifier).join(STATUS_FILE_NAME))
+}
+
+/// 读取正在运行的实例的状态快照，快照不存在或过期时返回 None
+fn read_snapshot(identifier: &str, now: u64) -> Option<StatusSnapshot> {
+  status_file_path(identifier)
+    .and_then(|path| fs::read_to_string(path).ok())
+    .and_then(|json| serde_json::from_str::<StatusSnapshot>(&json).ok())
+    .filter(|s| now.saturating_sub(s.updated_at) < STATUS_FILE_STALE_MS)
+}
+
+fn write_snapshot(path: &Path, status: CounterStatus) -> Result<(), String> {
+  let snapshot = StatusSnapshot {
+    status,
+    updated_at: now_ms(),
+  };
+  let json = serde_json::to_string(&snapshot).map_err(|e| e.to_string())?;
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+  }
+  // 先写临时文件再替换，避免命令行读到写了一半的内容
+  let tmp = path.with_extension("json.tmp");
+  fs::write(&tmp, json).map_err(|e| e.to_string())?;
+  fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// 定期把计时器状态写入快照文件，供 `restloop status` 读取
+pub fn setup_status_file(app: &mut App, bus: &CounterBus) {
+  let Some(path) = status_file_path(&app.config().identifier) else {
+    return;
+  };
+  let app = app.handle().clone();
+  let events = bus.subscribe();
+  std::thread::spawn(move || {
+    loop {
+      let status = match events.recv_timeout(STATUS_FILE_REFRESH) {
+        Ok(event) => event.status,
+        Err(RecvTimeoutError::Timeout) => app.state::<CounterBusState>().status(),
+        Err(RecvTimeoutError::Disconnected) => break,
+      };
+      if let Err(e) = write_snapshot(&path, status) {
+        eprintln!("failed to write status file: {}", e);
+      }
+    }
+  });
+}
+
+/// Windows 上发布版本没有控制台，输出前需要附加到启动它的终端
+fn attach_console() {
+  #[cfg(windows)]
+  unsafe {
+    winapi::um::wincon::AttachConsole(winapi::um::wincon::ATTACH_PARENT_PROCESS);
+  }
+}
+
+/// 输出用法，有参数错误时一并输出，返回进程退出码
+pub fn print_usage(error: Option<&str>) -> i32 {
+  attach_console();
+  match error {
+    Some(error) => {
+      eprintln!("{}\n{}", error, USAGE);
+      2
+    }
+    None => {
+      println!("{}", USAGE);
+      0
+    }
+  }
+}
+
+/// 输出正在运行的实例的状态，返回进程退出码
+pub fn print_status(identifier: &str, json: bool) -> i32 {
+  attach_console();
+  let now = now_ms();
+  let Some(mut snapshot) = read_snapshot(identifier, now) else {
+    eprintln!("RestLoop 没有在运行");
+    return 1;
+  };
+
+  // 快照每隔几秒才写入一次，按经过的时间推算当前的进度。离开期间工作时间不累计。
+  let status = &mut snapshot.status;
+  let ticking = match status.phase {
+    Phase::Counting => {
+      !status.paused && !status.off_hours && !status.idle && status.deferred_by.is_none()
+    }
+    Phase::Blocking => true,
+    Phase::BlockEnd => false,
+  };
+  if ticking {
+    let passed = (now.saturating_sub(snapshot.updated_at) / 1000) as u32;
+    status.elapsed_secs += passed;
+    status.remaining_secs = status.remaining_secs.saturating_sub(passed);
+  }
+
+  if json {
+    match serde_json::to_string(&snapshot) {
+      Ok(json) => println!("{}", json),
+      Err(e) => {
+        eprintln!("{}", e);
+        return 1;
+      }
+    }
+  } else {
+    let status = &snapshot.status;
+    let phase = serde_json::to_value(status.phase)
+      .ok()
+      .and_then(|v| v.as_str().map(str::to_string))
+      .unwrap_or_default();
+    let mut line = format!("{} {}", phase, status.remaining_secs);
+    if status.off_hours {
+      line.push_str(" offHours");
+    } else if status.paused {
+      line.push_str(" paused");
+    } else if status.idle {
+      line.push_str(" idle");
+    }
+    println!("{}", line);
+  }
+  0
+}
+
+/// 检查控制指令能否被正在运行的实例执行，返回 None 时继续转发，否则返回进程退出码
+pub fn check_control(identifier: &str, command: &CliCommand) -> Option<i32> {
+  let Some(snapshot) = read_snapshot(identifier, now_ms()) else {
+    attach_console();
+    eprintln!("RestLoop 没有在运行");
+    return Some(1);
+  };
+  let reason = command.rejection(&snapshot.status)?;
+  attach_console();
+  eprintln!("{}", reason);
+  Some(1)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> Result<Option<CliCommand>, String> {
+    parse_args(["restloop"].iter().chain(args).map(|s| s.to_string()))
+  }
+
+  #[test]
+  fn durations() {
+    assert_eq!(parse_duration("90s"), Ok(90));
+    assert_eq!(parse_duration("30m"), Ok(30 * 60));
+    assert_eq!(parse_duration("2h"), Ok(2 * 3600));
+    // 不带单位时按分钟
+    assert_eq!(parse_duration("15"), Ok(15 * 60));
+    assert!(parse_duration("0m").is_err());
+    assert!(parse_duration("").is_err());
+    assert!(parse_duration("m").is_err());
+    assert!(parse_duration("1d").is_err());
+    assert!(parse_duration("1h30m").is_err());
+    assert!(parse_duration("-5m").is_err());
+    assert!(parse_duration("99999999h").is_err());
+  }
+
+  #[test]
+  fn commands() {
+    assert_eq!(parse(&[]), Ok(None));
+    assert_eq!(parse(&["pause"]), Ok(Some(CliCommand::Pause(None))));
+    assert_eq!(
+      parse(&["pause", "1h"]),
+      Ok(Some(CliCommand::Pause(Some(3600))))
+    );
+    assert_eq!(
+      parse(&["pause", "tomorrow"]),
+      Ok(Some(CliCommand::PauseUntilNextWorkday))
+    );
+    assert_eq!(parse(&["resume"]), Ok(Some(CliCommand::Resume)));
+    assert_eq!(parse(&["reset"]), Ok(Some(CliCommand::Reset)));
+    assert_eq!(parse(&["break-now"]), Ok(Some(CliCommand::BreakNow)));
+    assert_eq!(
+      parse(&["status"]),
+      Ok(Some(CliCommand::Status { json: false }))
+    );
+    assert_eq!(
+      parse(&["status", "--json"]),
+      Ok(Some(CliCommand::Status { json: true }))
+    );
+    assert_eq!(parse(&["--help"]), Ok(Some(CliCommand::Help)));
+  }
+
+  #[test]
+  fn bad_arguments() {
+    assert!(parse(&["pause", "soon"]).is_err());
+    assert!(parse(&["pause", "1h", "2h"]).is_err());
+    assert!(parse(&["resume", "now"]).is_err());
+    assert!(parse(&["status", "--yaml"]).is_err());
+    // 系统或自启动传入的其它参数按正常启动处理
+    assert_eq!(parse(&["--autostart"]), Ok(None));
+  }
+
+  #[test]
+  fn rejected_commands() {
+    let working = CounterStatus::default();
+    assert_eq!(CliCommand::BreakNow.rejection(&working), None);
+    assert_eq!(CliCommand::Reset.rejection(&working), None);
+
+    let resting = CounterStatus {
+      phase: Phase::Blocking,
+      ..working
+    };
+    assert!(CliCommand::Reset.rejection(&resting).is_some());
+    assert!(CliCommand::BreakNow.rejection(&resting).is_some());
+    assert!(CliCommand::Pause(None).rejection(&resting).is_some());
+
+    let strict = CounterStatus {
+      strict_break: true,
+      ..resting
+    };
+    assert!(CliCommand::Resume.rejection(&strict).is_some());
+
+    let off_hours = CounterStatus {
+      off_hours: true,
+      ..working
+    };
+    assert!(
+      CliCommand::PauseUntilNextWorkday
+        .rejection(&off_hours)
+        .is_some()
+    );
+    assert_eq!(CliCommand::Resume.rejection(&off_hours), None);
+  }
+}
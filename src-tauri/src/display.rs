@@ -0,0 +1,87 @@
+use display_info::DisplayInfo;
+
+const COUNTER_WINDOW_WIDTH: f32 = 142f32;
+const COUNTER_WINDOW_HEIGHT: f32 = 70f32;
+const COUNTER_WINDOW_BOTTOM: f32 = 80f32;
+const COUNTER_WINDOW_RIGHT: f32 = 30f32;
+
+/// 主屏幕，计时小窗和主遮挡窗口都放在这里
+#[derive(Debug, Clone, PartialEq)]
+pub struct CounterDisplay {
+  pub scale: f32,
+  /// left, top, width, height
+  pub screen_rect: ((f32, f32), (f32, f32)),
+  /// left, top, width, height
+  pub counting_rect: ((f32, f32), (f32, f32)),
+  /// 物理像素的 left, top, width, height，用于和系统窗口坐标比较
+  pub physical_rect: ((i32, i32), (u32, u32)),
+}
+
+/// 休息时需要额外遮挡的屏幕
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockerDisplay {
+  pub id: u32,
+  pub scale: f32,
+  /// left, top, width, height
+  pub screen_rect: ((f32, f32), (f32, f32)),
+}
+
+/// 当前所有屏幕的布局，插拔显示器或修改分辨率、缩放后会发生变化
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayLayout {
+  pub primary: CounterDisplay,
+  pub others: Vec<BlockerDisplay>,
+}
+
+fn logical_rect(d: &DisplayInfo) -> ((f32, f32), (f32, f32)) {
+  let scale = d.scale_factor;
+  (
+    ((d.x as f32) / scale, (d.y as f32) / scale),
+    ((d.width as f32) / scale, (d.height as f32) / scale),
+  )
+}
+
+impl DisplayLayout {
+  /// 读取当前的屏幕布局。没有标记为主屏幕时使用第一个屏幕。
+  pub fn query() -> Option<Self> {
+    let displays = DisplayInfo::all().ok()?;
+    let primary_display = displays
+      .iter()
+      .find(|d| d.is_primary)
+      .or_else(|| displays.first())?;
+
+    let scale = primary_display.scale_factor;
+    let ((left, top), (screen_width, screen_height)) = logical_rect(primary_display);
+    let width = COUNTER_WINDOW_WIDTH / scale;
+    let height = COUNTER_WINDOW_HEIGHT / scale;
+    let bottom = COUNTER_WINDOW_BOTTOM / scale;
+    let right = COUNTER_WINDOW_RIGHT / scale;
+
+    let primary = CounterDisplay {
+      scale,
+      screen_rect: ((left, top), (screen_width - 1.0, screen_height - 1.0)),
+      counting_rect: (
+        (
+          left + screen_width - width - right,
+          top + screen_height - bottom - height,
+        ),
+        (width, height),
+      ),
+      physical_rect: (
+        (primary_display.x, primary_display.y),
+        (primary_display.width, primary_display.height),
+      ),
+    };
+    let others = displays
+      .iter()
+      .filter(|d| d.id != primary_display.id)
+      .map(|d| BlockerDisplay {
+        id: d.id,
+        scale: d.scale_factor,
+        screen_rect: logical_rect(d),
+      })
+      .collect();
+
+    Some(Self { primary, others })
+  }
+}
@@ -0,0 +1,362 @@
+//! 休息期间的紧急退出方式。
+//!
+//! 每种方式实现 [`Escape`]，在主屏幕的遮挡窗口上绘制提示并处理输入，
+//! 完成后由计时器结束本次休息。
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use eframe::egui::{Color32, Context, FontId, Key, RichText, TextEdit, Ui};
+
+use crate::settings::{EscapeMethod, Settings};
+
+/// 一帧内所有屏幕上 Escape 键的状态
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EscapeInput {
+  /// 本帧按下了 Escape
+  pub pressed: bool,
+  /// Escape 处于按住状态
+  pub down: bool,
+}
+
+impl EscapeInput {
+  pub fn read(ctx: &Context) -> Self {
+    ctx.input(|i| Self {
+      pressed: i.key_pressed(Key::Escape),
+      down: i.key_down(Key::Escape),
+    })
+  }
+
+  pub fn merge(self, other: Self) -> Self {
+    Self {
+      pressed: self.pressed || other.pressed,
+      down: self.down || other.down,
+    }
+  }
+}
+
+pub trait Escape {
+  /// 处理本帧的输入并绘制提示，返回 true 表示完成了紧急退出操作
+  fn update(&mut self, ui: &mut Ui, input: EscapeInput, font_size: f32) -> bool;
+  /// 新的一次休息开始时清空进度
+  fn reset(&mut self);
+}
+
+/// 根据配置创建紧急退出方式
+pub fn escape_from_settings(settings: &Settings) -> Box<dyn Escape + Send> {
+  match settings.escape_method {
+    EscapeMethod::Repeat => Box::new(RepeatEscape {
+      count: settings.escape_count,
+      pressed: 0,
+    }),
+    EscapeMethod::Hold => Box::new(HoldEscape {
+      hold: Duration::from_secs(settings.escape_hold_secs as u64),
+      since: None,
+    }),
+    EscapeMethod::Phrase => Box::new(PhraseEscape {
+      phrase: settings.escape_phrase.trim().to_string(),
+      text: String::new(),
+      open: false,
+    }),
+    EscapeMethod::Arithmetic => Box::new(ArithmeticEscape::new()),
+  }
+}
+
+fn hint(ui: &mut Ui, text: impl Into<String>, font_size: f32) {
+  ui.label(RichText::new(text).size(font_size).color(Color32::RED));
+}
+
+/// 单行输入框，始终保持焦点，回车时返回 true
+pub fn submit_field(ui: &mut Ui, text: &mut String, font_size: f32) -> bool {
+  let res = ui.add(
+    TextEdit::singleline(text)
+      .font(FontId::proportional(font_size))
+      .desired_width(font_size * 12.0),
+  );
+  let submitted = res.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+  res.request_focus();
+  submitted
+}
+
+/// 连按 Escape 指定次数
+pub struct RepeatEscape {
+  count: u32,
+  pressed: u32,
+}
+
+impl RepeatEscape {
+  /// 记录本帧的按键，返回是否已经按够次数
+  fn press(&mut self, input: EscapeInput) -> bool {
+    if input.pressed {
+      self.pressed += 1;
+    }
+    self.pressed >= self.count
+  }
+}
+
+impl Escape for RepeatEscape {
+  fn update(&mut self, ui: &mut Ui, input: EscapeInput, font_size: f32) -> bool {
+    let done = self.press(input);
+    if self.pressed > (self.count >> 1) {
+      hint(ui, "即将紧急退出", font_size);
+    }
+    done
+  }
+
+  fn reset(&mut self) {
+    self.pressed = 0;
+  }
+}
+
+/// 按住 Escape 指定时长，中途松开需要重新计时
+pub struct HoldEscape {
+  hold: Duration,
+  since: Option<Instant>,
+}
+
+impl HoldEscape {
+  /// 还需要按住多久，没有按住时返回 None，按够时长时返回零
+  fn left(&mut self, input: EscapeInput, now: Instant) -> Option<Duration> {
+    if !input.down {
+      self.since = None;
+      return None;
+    }
+    let held = now.saturating_duration_since(*self.since.get_or_insert(now));
+    Some(self.hold.saturating_sub(held))
+  }
+}
+
+impl Escape for HoldEscape {
+  fn update(&mut self, ui: &mut Ui, input: EscapeInput, font_size: f32) -> bool {
+    let Some(left) = self.left(input, Instant::now()) else {
+      return false;
+    };
+    if left.is_zero() {
+      return true;
+    }
+    hint(
+      ui,
+      format!("继续按住 Escape {} 秒紧急退出", left.as_secs_f32().ceil()),
+      font_size,
+    );
+    ui.ctx().request_repaint();
+    false
+  }
+
+  fn reset(&mut self) {
+    self.since = None;
+  }
+}
+
+/// 按下 Escape 后输入确认语句
+pub struct PhraseEscape {
+  phrase: String,
+  text: String,
+  open: bool,
+}
+
+impl PhraseEscape {
+  /// 按下 Escape 时打开或关闭输入框，返回输入框是否打开
+  fn toggle(&mut self, input: EscapeInput) -> bool {
+    if input.pressed {
+      self.open = !self.open;
+      self.text.clear();
+    }
+    self.open
+  }
+
+  /// 检查输入的语句，输错时清空重新输入
+  fn submit(&mut self) -> bool {
+    if self.text.trim() == self.phrase {
+      return true;
+    }
+    self.text.clear();
+    false
+  }
+}
+
+impl Escape for PhraseEscape {
+  fn update(&mut self, ui: &mut Ui, input: EscapeInput, font_size: f32) -> bool {
+    if !self.toggle(input) {
+      return false;
+    }
+    hint(
+      ui,
+      format!("输入「{}」后回车紧急退出", self.phrase),
+      font_size,
+    );
+    submit_field(ui, &mut self.text, font_size) && self.submit()
+  }
+
+  fn reset(&mut self) {
+    self.text.clear();
+    self.open = false;
+  }
+}
+
+/// 按下 Escape 后答对一道算术题，答错会换一道
+pub struct ArithmeticEscape {
+  seed: u64,
+  /// a × b + c
+  question: (u64, u64, u64),
+  text: String,
+  open: bool,
+}
+
+impl ArithmeticEscape {
+  fn new() -> Self {
+    let seed = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_nanos() as u64)
+      .unwrap_or_default();
+    Self {
+      seed: seed | 1,
+      question: (0, 0, 0),
+      text: String::new(),
+      open: false,
+    }
+  }
+
+  /// xorshift，出题用不需要更好的随机数
+  fn random(&mut self, min: u64, max: u64) -> u64 {
+    self.seed ^= self.seed << 13;
+    self.seed ^= self.seed >> 7;
+    self.seed ^= self.seed << 17;
+    min + self.seed % (max - min + 1)
+  }
+
+  fn next_question(&mut self) {
+    self.question = (self.random(12, 99), self.random(3, 9), self.random(10, 99));
+    self.text.clear();
+  }
+
+  fn answer(&self) -> u64 {
+    let (a, b, c) = self.question;
+    a * b + c
+  }
+
+  /// 按下 Escape 时打开或关闭题目，每次打开都换一道题，返回题目是否打开
+  fn toggle(&mut self, input: EscapeInput) -> bool {
+    if input.pressed {
+      self.open = !self.open;
+      self.next_question();
+    }
+    self.open
+  }
+
+  /// 检查输入的答案，答错时换一道题
+  fn submit(&mut self) -> bool {
+    if self.text.trim().parse::<u64>() == Ok(self.answer()) {
+      return true;
+    }
+    self.next_question();
+    false
+  }
+}
+
+impl Escape for ArithmeticEscape {
+  fn update(&mut self, ui: &mut Ui, input: EscapeInput, font_size: f32) -> bool {
+    if !self.toggle(input) {
+      return false;
+    }
+    let (a, b, c) = self.question;
+    hint(
+      ui,
+      format!("{} × {} + {} = ?  答对后回车紧急退出", a, b, c),
+      font_size,
+    );
+    submit_field(ui, &mut self.text, font_size) && self.submit()
+  }
+
+  fn reset(&mut self) {
+    self.text.clear();
+    self.open = false;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PRESS: EscapeInput = EscapeInput {
+    pressed: true,
+    down: true,
+  };
+  const HELD: EscapeInput = EscapeInput {
+    pressed: false,
+    down: true,
+  };
+  const NONE: EscapeInput = EscapeInput {
+    pressed: false,
+    down: false,
+  };
+
+  #[test]
+  fn repeat_needs_every_press() {
+    let mut escape = RepeatEscape {
+      count: 3,
+      pressed: 0,
+    };
+    assert!(!escape.press(PRESS));
+    // 按住不放不算多按
+    assert!(!escape.press(HELD));
+    assert!(!escape.press(PRESS));
+    assert!(escape.press(PRESS));
+
+    escape.reset();
+    assert!(!escape.press(PRESS));
+  }
+
+  #[test]
+  fn hold_restarts_when_released() {
+    let mut escape = HoldEscape {
+      hold: Duration::from_secs(3),
+      since: None,
+    };
+    let start = Instant::now();
+    let at = |secs: u64| start + Duration::from_secs(secs);
+    assert_eq!(escape.left(PRESS, at(0)), Some(Duration::from_secs(3)));
+    assert_eq!(escape.left(HELD, at(2)), Some(Duration::from_secs(1)));
+    // 中途松开需要重新计时
+    assert_eq!(escape.left(NONE, at(2)), None);
+    assert_eq!(escape.left(PRESS, at(3)), Some(Duration::from_secs(3)));
+    assert_eq!(escape.left(HELD, at(5)), Some(Duration::from_secs(1)));
+    assert_eq!(escape.left(HELD, at(6)), Some(Duration::ZERO));
+  }
+
+  #[test]
+  fn phrase_must_match() {
+    let mut escape = PhraseEscape {
+      phrase: "我要退出".into(),
+      text: String::new(),
+      open: false,
+    };
+    assert!(!escape.toggle(NONE));
+    assert!(escape.toggle(PRESS));
+    escape.text = "我要".into();
+    assert!(!escape.submit());
+    assert!(escape.text.is_empty());
+    escape.text = " 我要退出 ".into();
+    assert!(escape.submit());
+
+    // 再按一次 Escape 关闭输入框并清空
+    assert!(!escape.toggle(PRESS));
+    assert!(escape.text.is_empty());
+  }
+
+  #[test]
+  fn arithmetic_changes_question_on_wrong_answer() {
+    let mut escape = ArithmeticEscape::new();
+    assert!(escape.toggle(PRESS));
+    let (a, b, c) = escape.question;
+    assert!((12..=99).contains(&a) && (3..=9).contains(&b) && (10..=99).contains(&c));
+
+    let question = escape.question;
+    escape.text = (escape.answer() + 1).to_string();
+    assert!(!escape.submit());
+    assert!(escape.text.is_empty());
+    assert_ne!(escape.question, question);
+
+    escape.text = escape.answer().to_string();
+    assert!(escape.submit());
+  }
+}
@@ -0,0 +1,478 @@
+//! 导出历史记录为 CSV、JSON 或 iCalendar，方便在表格或日历软件中查看。
+
+use std::{fs, path::PathBuf};
+
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::Deserialize;
+use tauri::{AppHandle, Manager, Runtime, State};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::{
+  constant::APP_TITLE,
+  history::{BreakEnd, History, HistoryEntry, HistoryRecord, HistoryState, WorkEnd, now_ms},
+  timer::local_day,
+};
+
+/// 从托盘导出时包含的天数
+const TRAY_EXPORT_DAYS: u64 = 30;
+const DATE_FORMAT: &'static str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+  Csv,
+  Json,
+  Ics,
+}
+
+impl ExportFormat {
+  fn extension(self) -> &'static str {
+    match self {
+      ExportFormat::Csv => "csv",
+      ExportFormat::Json => "json",
+      ExportFormat::Ics => "ics",
+    }
+  }
+}
+
+/// 记录归属的时间：工作和休息按开始时间，其它按记录时间
+fn record_start(entry: &HistoryEntry) -> u64 {
+  match entry.record {
+    HistoryRecord::Work { started_at, .. } | HistoryRecord::Break { started_at, .. } => started_at,
+    _ => entry.at,
+  }
+}
+
+/// 读取本地日期 `from` 到 `to`（含两端）之间的记录
+fn entries_between(history: &History, from: NaiveDate, to: NaiveDate) -> Vec<HistoryEntry> {
+  let since = from
+    .and_time(NaiveTime::MIN)
+    .and_local_timezone(Local)
+    .earliest()
+    .map_or(0, |d| d.timestamp_millis() as u64);
+  history
+    .read_since(since)
+    .into_iter()
+    .filter(|e| local_day(record_start(e)).is_some_and(|d| d >= from && d <= to))
+    .collect()
+}
+
+fn work_end_name(ended: WorkEnd) -> &'static str {
+  match ended {
+    WorkEnd::Break => "break",
+    WorkEnd::Reset => "reset",
+    WorkEnd::Credited => "credited",
+    WorkEnd::OffHours => "offHours",
+  }
+}
+
+fn break_end_name(ended: &BreakEnd) -> &'static str {
+  match ended {
+    BreakEnd::Completed => "completed",
+    BreakEnd::Escaped { .. } => "escaped",
+    BreakEnd::Postponed => "postponed",
+    BreakEnd::OffHours => "offHours",
+  }
+}
+
+fn local_time(ms: u64) -> String {
+  Local
+    .timestamp_millis_opt(ms as i64)
+    .single()
+    .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+    .unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+  if value.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", value.replace('"', "\"\""))
+  } else {
+    value.to_string()
+  }
+}
+
+/// 暂停、推迟等没有时长的记录
+fn event_row(kind: &str, at: String, cycle: Option<u32>, process: Option<String>) -> [String; 11] {
+  [
+    kind.into(),
+    at,
+    String::new(),
+    String::new(),
+    String::new(),
+    cycle.map(|c| c.to_string()).unwrap_or_default(),
+    String::new(),
+    String::new(),
+    String::new(),
+    String::new(),
+    process.unwrap_or_default(),
+  ]
+}
+
+fn to_csv(entries: &[HistoryEntry]) -> String {
+  let mut out = String::from(
+    "type,start,end,durationSecs,pausedSecs,cycle,long,ended,escapeMethod,reason,process\n",
+  );
+  for entry in entries {
+    let at = local_time(entry.at);
+    let row: [String; 11] = match &entry.record {
+      HistoryRecord::Work {
+        cycle,
+        started_at,
+        duration_secs,
+        paused_secs,
+        ended,
+        ..
+      } => [
+        "work".into(),
+        local_time(*started_at),
+        at,
+        duration_secs.to_string(),
+        paused_secs.to_string(),
+        cycle.to_string(),
+        String::new(),
+        work_end_name(*ended).into(),
+        String::new(),
+        String::new(),
+        String::new(),
+      ],
+      HistoryRecord::Break {
+        cycle,
+        long,
+        started_at,
+        duration_secs,
+        ended,
+      } => {
+        let (method, reason) = match ended {
+          BreakEnd::Escaped { method, reason, .. } => (
+            serde_json::to_value(method)
+              .ok()
+              .and_then(|v| v.as_str().map(str::to_string))
+              .unwrap_or_default(),
+            reason.clone().unwrap_or_default(),
+          ),
+          _ => (String::new(), String::new()),
+        };
+        [
+          "break".into(),
+          local_time(*started_at),
+          at,
+          duration_secs.to_string(),
+          String::new(),
+          cycle.to_string(),
+          long.to_string(),
+          break_end_name(ended).into(),
+          method,
+          reason,
+          String::new(),
+        ]
+      }
+      HistoryRecord::Paused => event_row("paused", at, None, None),
+      HistoryRecord::Resumed => event_row("resumed", at, None, None),
+      HistoryRecord::Postponed { cycle, .. } => event_row("postponed", at, Some(*cycle), None),
+      HistoryRecord::Deferred { process, .. } => event_row("deferred", at, None, process.clone()),
+    };
+    let row: Vec<String> = row.iter().map(|v| csv_field(v)).collect();
+    out.push_str(&row.join(","));
+    out.push('\n');
+  }
+  out
+}
+
+fn ics_time(ms: u64) -> String {
+  Utc
+    .timestamp_millis_opt(ms as i64)
+    .single()
+    .map(|d| d.format("%Y%m%dT%H%M%SZ").to_string())
+    .unwrap_or_default()
+}
+
+fn ics_text(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace(';', "\\;")
+    .replace(',', "\\,")
+    .replace("\r\n", "\\n")
+    .replace(['\r', '\n'], "\\n")
+}
+
+/// 按 RFC 5545 把超过 75 字节的行折行
+fn ics_line(out: &mut String, line: &str) {
+  let mut width = 0;
+  for c in line.chars() {
+    if width + c.len_utf8() > 75 {
+      out.push_str("\r\n ");
+      width = 1;
+    }
+    out.push(c);
+    width += c.len_utf8();
+  }
+  out.push_str("\r\n");
+}
+
+/// 每段工作和每次休息都是一个日历事件
+fn to_ics(entries: &[HistoryEntry]) -> String {
+  let mut out = String::new();
+  let stamp = ics_time(now_ms());
+  ics_line(&mut out, "BEGIN:VCALENDAR");
+  ics_line(&mut out, "VERSION:2.0");
+  ics_line(&mut out, "PRODID:-//RestLoop//History//ZH");
+  for entry in entries {
+    let (kind, started_at, summary, description) = match &entry.record {
+      HistoryRecord::Work {
+        cycle,
+        started_at,
+        paused_secs,
+        ended,
+        ..
+      } => (
+        "work",
+        *started_at,
+        format!("工作（第 {} 轮）", cycle),
+        format!(
+          "暂停 {} 秒，结束方式：{}",
+          paused_secs,
+          work_end_name(*ended)
+        ),
+      ),
+      HistoryRecord::Break {
+        long,
+        started_at,
+        ended,
+        ..
+      } => {
+        let mut description = format!("结束方式：{}", break_end_name(ended));
+        if let BreakEnd::Escaped {
+          reason: Some(reason),
+          ..
+        } = ended
+        {
+          description.push_str(&format!("\n原因：{}", reason));
+        }
+        (
+          "break",
+          *started_at,
+          if *long { "长休息" } else { "休息" }.to_string(),
+          description,
+        )
+      }
+      _ => continue,
+    };
+    ics_line(&mut out, "BEGIN:VEVENT");
+    ics_line(&mut out, &format!("UID:{}-{}@restloop", kind, started_at));
+    ics_line(&mut out, &format!("DTSTAMP:{}", stamp));
+    ics_line(&mut out, &format!("DTSTART:{}", ics_time(started_at)));
+    ics_line(&mut out, &format!("DTEND:{}", ics_time(entry.at)));
+    ics_line(&mut out, &format!("SUMMARY:{}", ics_text(&summary)));
+    ics_line(&mut out, &format!("DESCRIPTION:{}", ics_text(&description)));
+    ics_line(&mut out, "END:VEVENT");
+  }
+  ics_line(&mut out, "END:VCALENDAR");
+  out
+}
+
+/// 只导出到下载目录，没有时导出到文档目录
+fn export_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+  app
+    .path()
+    .download_dir()
+    .or_else(|_| app.path().document_dir())
+    .map_err(|e| format!("找不到导出目录：{}", e))
+}
+
+fn default_file_name(format: ExportFormat, from: NaiveDate, to: NaiveDate) -> String {
+  format!(
+    "restloop-history-{}-{}.{}",
+    from.format(DATE_FORMAT),
+    to.format(DATE_FORMAT),
+    format.extension()
+  )
+}
+
+/// 只保留文件名本身：去掉目录部分以及各平台文件名中不允许的字符，缺少扩展名时补上
+fn sanitize_file_name(name: &str, format: ExportFormat) -> Result<String, String> {
+  let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+  let name: String = name
+    .chars()
+    .filter(|c| !c.is_control() && !matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*'))
+    .collect();
+  let name = name.trim().trim_matches('.');
+  if name.is_empty() {
+    return Err("文件名不能为空".into());
+  }
+  let extension = format!(".{}", format.extension());
+  if name.to_lowercase().ends_with(&extension) {
+    Ok(name.to_string())
+  } else {
+    Ok(format!("{}{}", name, extension))
+  }
+}
+
+/// 导出本地日期 `from` 到 `to`（含两端）之间的记录到导出目录，返回写入的文件路径
+pub fn export_history<R: Runtime>(
+  app: &AppHandle<R>,
+  history: &History,
+  format: ExportFormat,
+  from: NaiveDate,
+  to: NaiveDate,
+  file_name: Option<&str>,
+) -> Result<PathBuf, String> {
+  if from > to {
+    return Err("开始日期不能晚于结束日期".into());
+  }
+  let entries = entries_between(history, from, to);
+  let content = match format {
+    ExportFormat::Csv => to_csv(&entries),
+    ExportFormat::Json => serde_json::to_string_pretty(&entries).map_err(|e| e.to_string())?,
+    ExportFormat::Ics => to_ics(&entries),
+  };
+  let file_name = match file_name {
+    Some(name) => sanitize_file_name(name, format)?,
+    None => default_file_name(format, from, to),
+  };
+  let path = export_dir(app)?.join(file_name);
+  fs::write(&path, content).map_err(|e| format!("写入 {} 失败：{}", path.display(), e))?;
+  Ok(path)
+}
+
+/// 从托盘导出最近 30 天的记录到下载目录，完成后通过系统通知告知结果
+pub fn export_recent_history<R: Runtime>(app: &AppHandle<R>, format: ExportFormat) {
+  let app = app.clone();
+  std::thread::spawn(move || {
+    let history = app.state::<HistoryState>();
+    let result = local_day(now_ms())
+      .ok_or_else(|| "无法获取当前日期".to_string())
+      .and_then(|to| {
+        let from = to - chrono::Days::new(TRAY_EXPORT_DAYS - 1);
+        export_history(&app, &history, format, from, to, None)
+      });
+    let body = match result {
+      Ok(path) => format!("历史记录已导出到 {}", path.display()),
+      Err(e) => format!("导出历史记录失败：{}", e),
+    };
+    let _ = app
+      .notification()
+      .builder()
+      .title(APP_TITLE)
+      .body(body)
+      .show();
+  });
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+  NaiveDate::parse_from_str(value, DATE_FORMAT).map_err(|_| format!("日期格式错误：{}", value))
+}
+
+/// 导出 `from` 到 `to`（YYYY-MM-DD，含两端）之间的记录到下载目录。
+/// `file_name` 只能是文件名，其中的目录部分会被去掉，返回写入的文件路径。
+#[tauri::command]
+pub async fn tauri_export_history<R: Runtime>(
+  app: AppHandle<R>,
+  history: State<'_, HistoryState>,
+  format: ExportFormat,
+  from: String,
+  to: String,
+  file_name: Option<String>,
+) -> Result<String, String> {
+  let path = export_history(
+    &app,
+    &history,
+    format,
+    parse_date(&from)?,
+    parse_date(&to)?,
+    file_name.as_deref(),
+  )?;
+  Ok(path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{history::HISTORY_SCHEMA_VERSION, settings::EscapeMethod};
+
+  #[test]
+  fn csv_fields_are_quoted_when_needed() {
+    assert_eq!(csv_field("plain"), "plain");
+    assert_eq!(csv_field("a,b"), "\"a,b\"");
+    assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+    assert_eq!(csv_field("line\rbreak"), "\"line\rbreak\"");
+  }
+
+  #[test]
+  fn csv_escape_reason_stays_in_one_field() {
+    let entry = HistoryEntry {
+      v: HISTORY_SCHEMA_VERSION,
+      at: 1_704_110_700_000,
+      record: HistoryRecord::Break {
+        cycle: 2,
+        long: false,
+        started_at: 1_704_110_400_000,
+        duration_secs: 300,
+        ended: BreakEnd::Escaped {
+          method: EscapeMethod::Phrase,
+          reason: Some("会议, \"紧急\"".into()),
+          skipped_secs: 100,
+        },
+      },
+    };
+    let csv = to_csv(&[entry]);
+    let row = csv.lines().nth(1).unwrap();
+    assert!(row.starts_with("break,"));
+    assert!(row.ends_with(",300,,2,false,escaped,phrase,\"会议, \"\"紧急\"\"\","));
+  }
+
+  #[test]
+  fn ics_text_is_escaped() {
+    assert_eq!(ics_text("a;b,c\\d"), "a\\;b\\,c\\\\d");
+    assert_eq!(
+      ics_text("one\r\ntwo\nthree\rfour"),
+      "one\\ntwo\\nthree\\nfour"
+    );
+  }
+
+  #[test]
+  fn long_ics_lines_are_folded() {
+    let text = format!("DESCRIPTION:{}", "休息".repeat(30));
+    let mut out = String::new();
+    ics_line(&mut out, &text);
+    assert!(out.ends_with("\r\n"));
+    let lines: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+    assert!(lines.len() > 1);
+    assert!(lines.iter().all(|l| l.len() <= 75));
+    assert!(lines[1..].iter().all(|l| l.starts_with(' ')));
+    // 去掉折行后还原成原来的内容，多字节字符没有被拆开
+    let unfolded: String = lines
+      .iter()
+      .map(|l| l.strip_prefix(' ').unwrap_or(l))
+      .collect();
+    assert_eq!(unfolded, text);
+
+    let mut out = String::new();
+    ics_line(&mut out, "BEGIN:VEVENT");
+    assert_eq!(out, "BEGIN:VEVENT\r\n");
+  }
+
+  #[test]
+  fn export_file_name_cannot_leave_the_export_dir() {
+    let csv = ExportFormat::Csv;
+    assert_eq!(sanitize_file_name("history", csv).unwrap(), "history.csv");
+    assert_eq!(
+      sanitize_file_name("history.CSV", csv).unwrap(),
+      "history.CSV"
+    );
+    assert_eq!(
+      sanitize_file_name("../../.bashrc", ExportFormat::Json).unwrap(),
+      "bashrc.json"
+    );
+    assert_eq!(
+      sanitize_file_name("C:\\Windows\\win.ini", csv).unwrap(),
+      "win.ini.csv"
+    );
+    assert_eq!(
+      sanitize_file_name("/etc/a<b>?.ics", ExportFormat::Ics).unwrap(),
+      "ab.ics"
+    );
+    assert!(sanitize_file_name("..", csv).is_err());
+    assert!(sanitize_file_name("dir/", csv).is_err());
+  }
+}
@@ -0,0 +1,150 @@
+//! 全屏窗口检测。
+//!
+//! 主屏幕上有全屏窗口（演示、视频会议、全屏视频）时计时器会暂缓休息，
+//! 各平台的实现都放在这里，通过 [`FullscreenDetector`] 使用。
+
+use crate::display::CounterDisplay;
+
+pub trait FullscreenDetector {
+  /// 主屏幕上当前是否有全屏窗口
+  fn is_fullscreen(&mut self, display: &CounterDisplay) -> bool;
+}
+
+/// 不支持全屏检测的平台，以及 Wayland 等无法读取其它应用窗口状态的会话使用，总是返回 false
+#[cfg(not(windows))]
+pub struct NoFullscreenDetector;
+
+#[cfg(not(windows))]
+impl FullscreenDetector for NoFullscreenDetector {
+  fn is_fullscreen(&mut self, _display: &CounterDisplay) -> bool {
+    false
+  }
+}
+
+/// 读取 X11 当前激活窗口的 `_NET_WM_STATE`，判断是否带有 `_NET_WM_STATE_FULLSCREEN`
+#[cfg(target_os = "linux")]
+pub struct X11FullscreenDetector {
+  conn: x11rb::rust_connection::RustConnection,
+  root: u32,
+  net_active_window: u32,
+  net_wm_state: u32,
+  net_wm_state_fullscreen: u32,
+}
+
+#[cfg(target_os = "linux")]
+impl X11FullscreenDetector {
+  pub fn connect() -> Option<Self> {
+    use x11rb::{connection::Connection, protocol::xproto::ConnectionExt};
+
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots.get(screen)?.root;
+    let atom = |name: &[u8]| Some(conn.intern_atom(false, name).ok()?.reply().ok()?.atom);
+    let net_active_window = atom(b"_NET_ACTIVE_WINDOW")?;
+    let net_wm_state = atom(b"_NET_WM_STATE")?;
+    let net_wm_state_fullscreen = atom(b"_NET_WM_STATE_FULLSCREEN")?;
+    Some(Self {
+      conn,
+      root,
+      net_active_window,
+      net_wm_state,
+      net_wm_state_fullscreen,
+    })
+  }
+
+  fn query(&self, display: &CounterDisplay) -> Option<bool> {
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+
+    let active = self
+      .conn
+      .get_property(
+        false,
+        self.root,
+        self.net_active_window,
+        AtomEnum::WINDOW,
+        0,
+        1,
+      )
+      .ok()?
+      .reply()
+      .ok()?
+      .value32()?
+      .next()?;
+    if active == 0 {
+      return Some(false);
+    }
+    let fullscreen = self
+      .conn
+      .get_property(false, active, self.net_wm_state, AtomEnum::ATOM, 0, 64)
+      .ok()?
+      .reply()
+      .ok()?
+      .value32()?
+      .any(|state| state == self.net_wm_state_fullscreen);
+    if !fullscreen {
+      return Some(false);
+    }
+
+    // 窗口中心落在主屏幕内才算
+    let geometry = self.conn.get_geometry(active).ok()?.reply().ok()?;
+    let pos = self
+      .conn
+      .translate_coordinates(active, self.root, 0, 0)
+      .ok()?
+      .reply()
+      .ok()?;
+    let cx = pos.dst_x as i32 + geometry.width as i32 / 2;
+    let cy = pos.dst_y as i32 + geometry.height as i32 / 2;
+    let ((left, top), (width, height)) = display.physical_rect;
+    Some(cx >= left && cx < left + width as i32 && cy >= top && cy < top + height as i32)
+  }
+}
+
+#[cfg(target_os = "linux")]
+impl FullscreenDetector for X11FullscreenDetector {
+  fn is_fullscreen(&mut self, display: &CounterDisplay) -> bool {
+    self.query(display).unwrap_or(false)
+  }
+}
+
+/// 通过 SHQueryUserNotificationState 判断是否处于全屏或演示模式
+#[cfg(windows)]
+pub struct WindowsFullscreenDetector;
+
+#[cfg(windows)]
+impl FullscreenDetector for WindowsFullscreenDetector {
+  fn is_fullscreen(&mut self, _display: &CounterDisplay) -> bool {
+    use winapi::um::shellapi::{
+      QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_RUNNING_D3D_FULL_SCREEN, SHQueryUserNotificationState,
+    };
+
+    let mut state = 0;
+    unsafe {
+      if SHQueryUserNotificationState(&mut state) != 0 {
+        return false;
+      }
+    }
+    matches!(
+      state,
+      QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE
+    )
+  }
+}
+
+/// 返回当前平台可用的全屏检测实现
+pub fn system_fullscreen_detector() -> Box<dyn FullscreenDetector + Send> {
+  #[cfg(target_os = "linux")]
+  {
+    if let Some(detector) = X11FullscreenDetector::connect() {
+      return Box::new(detector);
+    }
+    Box::new(NoFullscreenDetector)
+  }
+  #[cfg(windows)]
+  {
+    Box::new(WindowsFullscreenDetector)
+  }
+  #[cfg(not(any(target_os = "linux", windows)))]
+  {
+    Box::new(NoFullscreenDetector)
+  }
+}
@@ -0,0 +1,412 @@
+//! 休息历史记录。
+//!
+//! 每段工作、每次休息以及暂停、推迟、暂缓、紧急退出都会追加到应用数据目录下的
+//! `history.jsonl` 中，一行一条记录。每条记录带有 schema 版本号，
+//! 读取时跳过无法识别的记录；启动时按保留天数清理过期的记录。
+
+use std::{
+  fs::{self, File, OpenOptions},
+  io::{BufRead, BufReader, Write},
+  path::PathBuf,
+  sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{App, Manager};
+
+use crate::{
+  message::{CounterBus, CounterStatus, EventKind},
+  settings::EscapeMethod,
+  timer::{DeferReason, Phase, WorkSpan, local_day},
+};
+
+/// 当前的记录格式版本，格式发生不兼容的变化时递增
+pub const HISTORY_SCHEMA_VERSION: u32 = 1;
+const HISTORY_FILE_NAME: &'static str = "history.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+  /// 记录格式版本
+  pub v: u32,
+  /// 记录时的 unix 毫秒时间戳
+  pub at: u64,
+  #[serde(flatten)]
+  pub record: HistoryRecord,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+  tag = "type",
+  rename_all = "camelCase",
+  rename_all_fields = "camelCase"
+)]
+pub enum HistoryRecord {
+  /// 一段工作结束
+  Work {
+    cycle: u32,
+    started_at: u64,
+    /// 实际工作的秒数，不含暂停、离开和休眠
+    duration_secs: u32,
+    /// 最长一次连续工作的秒数
+    #[serde(default)]
+    longest_stretch_secs: u32,
+    /// 其中暂停的秒数
+    paused_secs: u32,
+    ended: WorkEnd,
+  },
+  /// 一次休息结束
+  Break {
+    cycle: u32,
+    long: bool,
+    started_at: u64,
+    duration_secs: u32,
+    ended: BreakEnd,
+  },
+  Paused,
+  Resumed,
+  /// 推迟休息，`from_break` 表示是在休息中推迟的
+  Postponed {
+    cycle: u32,
+    from_break: bool,
+  },
+  /// 工作时长已到，暂缓进入休息
+  Deferred {
+    reason: DeferReason,
+    process: Option<String>,
+  },
+}
+
+/// 一段工作是怎样结束的
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WorkEnd {
+  /// 进入休息
+  Break,
+  /// 被手动重置
+  Reset,
+  /// 离开或休眠足够久，算作完成了一次休息
+  Credited,
+  /// 离开了工作时间
+  OffHours,
+}
+
+/// 一次休息是怎样结束的
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+  tag = "type",
+  rename_all = "camelCase",
+  rename_all_fields = "camelCase"
+)]
+pub enum BreakEnd {
+  Completed,
+  Escaped {
+    method: EscapeMethod,
+    reason: Option<String>,
+    skipped_secs: u32,
+  },
+  Postponed,
+  OffHours,
+}
+
+pub fn now_ms() -> u64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or_default()
+}
+
+fn secs_between(from_ms: u64, to_ms: u64) -> u32 {
+  (to_ms.saturating_sub(from_ms) / 1000) as u32
+}
+
+/// 历史记录文件，追加和清理通过锁串行执行
+pub struct History {
+  path: PathBuf,
+  lock: Mutex<()>,
+}
+
+pub type HistoryState = Arc<History>;
+
+impl History {
+  pub fn new(dir: PathBuf) -> Self {
+    Self {
+      path: dir.join(HISTORY_FILE_NAME),
+      lock: Mutex::new(()),
+    }
+  }
+
+  pub fn append(&self, record: HistoryRecord) -> Result<(), String> {
+    let entry = HistoryEntry {
+      v: HISTORY_SCHEMA_VERSION,
+      at: now_ms(),
+      record,
+    };
+    let mut line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+    line.push('\n');
+
+    let _guard = self.lock.lock().unwrap();
+    if let Some(dir) = self.path.parent() {
+      fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&self.path)
+      .and_then(|mut f| f.write_all(line.as_bytes()))
+      .map_err(|e| e.to_string())
+  }
+
+  /// 读取 `since_ms` 之后的记录，无法解析或版本更新的记录会被跳过
+  pub fn read_since(&self, since_ms: u64) -> Vec<HistoryEntry> {
+    let _guard = self.lock.lock().unwrap();
+    let Ok(file) = File::open(&self.path) else {
+      return Vec::new();
+    };
+    BufReader::new(file)
+      .lines()
+      .map_while(Result::ok)
+      .filter_map(|line| serde_json::from_str::<HistoryEntry>(&line).ok())
+      .filter(|e| e.v <= HISTORY_SCHEMA_VERSION && e.at >= since_ms)
+      .collect()
+  }
+
+  /// 删除 `before_ms` 之前的记录。先写入临时文件再替换，避免中途失败丢失数据。
+  pub fn prune(&self, before_ms: u64) -> Result<(), String> {
+    let _guard = self.lock.lock().unwrap();
+    let Ok(file) = File::open(&self.path) else {
+      return Ok(());
+    };
+    let mut kept = String::new();
+    let mut pruned = false;
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+      // 无法解析的记录原样保留，可能来自更新的版本
+      let expired = serde_json::from_str::<serde_json::Value>(&line)
+        .ok()
+        .and_then(|v| v.get("at").and_then(|at| at.as_u64()))
+        .is_some_and(|at| at < before_ms);
+      if expired {
+        pruned = true;
+      } else {
+        kept.push_str(&line);
+        kept.push('\n');
+      }
+    }
+    if !pruned {
+      return Ok(());
+    }
+    let tmp = self.path.with_extension("jsonl.tmp");
+    fs::write(&tmp, kept).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, &self.path).map_err(|e| e.to_string())
+  }
+
+  /// 今天已经推迟过的次数
+  pub fn postponed_today(&self) -> u32 {
+    let now = now_ms();
+    let today = local_day(now);
+    self
+      .read_since(now.saturating_sub(24 * 3600 * 1000))
+      .iter()
+      .filter(|e| matches!(e.record, HistoryRecord::Postponed { .. }) && local_day(e.at) == today)
+      .count() as u32
+  }
+}
+
+/// 正在进行中的工作或休息
+enum Ongoing {
+  Work {
+    cycle: u32,
+    started_at: u64,
+    paused_ms: u64,
+    paused_at: Option<u64>,
+  },
+  Break {
+    cycle: u32,
+    long: bool,
+    started_at: u64,
+  },
+}
+
+/// 根据计时器事件还原每段工作和休息的起止时间
+struct Tracker {
+  history: HistoryState,
+  ongoing: Option<Ongoing>,
+}
+
+impl Tracker {
+  fn start_work(&mut self, status: &CounterStatus, now: u64) {
+    self.ongoing = (!status.off_hours).then_some(Ongoing::Work {
+      cycle: status.cycle,
+      started_at: now,
+      paused_ms: 0,
+      paused_at: status.paused.then_some(now),
+    });
+  }
+
+  /// 结束正在进行的工作，工作时长取计时器记下的 `work`
+  fn end_work(&mut self, ended: WorkEnd, work: WorkSpan, now: u64) {
+    if !matches!(self.ongoing, Some(Ongoing::Work { .. })) {
+      return;
+    }
+    if let Some(Ongoing::Work {
+      cycle,
+      started_at,
+      paused_ms,
+      paused_at,
+    }) = self.ongoing.take()
+    {
+      let paused_ms = paused_ms + paused_at.map_or(0, |at| now.saturating_sub(at));
+      self.write(HistoryRecord::Work {
+        cycle,
+        started_at,
+        duration_secs: work.worked_secs,
+        longest_stretch_secs: work.longest_stretch_secs,
+        paused_secs: (paused_ms / 1000) as u32,
+        ended,
+      });
+    }
+  }
+
+  fn end_break(&mut self, ended: BreakEnd, now: u64) {
+    if !matches!(self.ongoing, Some(Ongoing::Break { .. })) {
+      return;
+    }
+    if let Some(Ongoing::Break {
+      cycle,
+      long,
+      started_at,
+    }) = self.ongoing.take()
+    {
+      self.write(HistoryRecord::Break {
+        cycle,
+        long,
+        started_at,
+        duration_secs: secs_between(started_at, now),
+        ended,
+      });
+    }
+  }
+
+  fn write(&self, record: HistoryRecord) {
+    if let Err(e) = self.history.append(record) {
+      eprintln!("failed to write history: {}", e);
+    }
+  }
+
+  fn handle(&mut self, kind: EventKind, status: CounterStatus) {
+    let now = now_ms();
+    // 启动时计时器可能就处于工作时间之外，此时不会有 WorkHoursEnded 事件
+    if status.off_hours && !matches!(kind, EventKind::WorkHoursEnded) {
+      self.ongoing = None;
+    }
+    match kind {
+      EventKind::PhaseChanged => match status.phase {
+        Phase::Blocking => {
+          self.end_work(WorkEnd::Break, status.last_work, now);
+          self.ongoing = Some(Ongoing::Break {
+            cycle: status.cycle,
+            long: status.long_break,
+            started_at: now,
+          });
+        }
+        Phase::BlockEnd => self.end_break(BreakEnd::Completed, now),
+        Phase::Counting => {
+          self.end_break(BreakEnd::Completed, now);
+          if self.ongoing.is_none() {
+            self.start_work(&status, now);
+          }
+        }
+      },
+      EventKind::Paused => {
+        if let Some(Ongoing::Work { paused_at, .. }) = self.ongoing.as_mut()
+          && paused_at.is_none()
+          && status.paused
+        {
+          *paused_at = Some(now);
+          self.write(HistoryRecord::Paused);
+        }
+      }
+      EventKind::Resumed => {
+        if let Some(Ongoing::Work {
+          paused_at,
+          paused_ms,
+          ..
+        }) = self.ongoing.as_mut()
+          && let Some(at) = paused_at.take()
+        {
+          *paused_ms += now.saturating_sub(at);
+          self.write(HistoryRecord::Resumed);
+        }
+      }
+      EventKind::Reset => {
+        self.end_work(WorkEnd::Reset, status.last_work, now);
+        self.start_work(&status, now);
+      }
+      EventKind::BreakCredited => {
+        self.end_work(WorkEnd::Credited, status.last_work, now);
+        self.start_work(&status, now);
+      }
+      EventKind::WorkHoursStarted => self.start_work(&status, now),
+      EventKind::WorkHoursEnded => {
+        self.end_work(WorkEnd::OffHours, status.last_work, now);
+        self.end_break(BreakEnd::OffHours, now);
+      }
+      EventKind::Postponed => {
+        let from_break = matches!(self.ongoing, Some(Ongoing::Break { .. }));
+        self.write(HistoryRecord::Postponed {
+          cycle: status.cycle,
+          from_break,
+        });
+        // 休息中推迟时，随后的 PhaseChanged 会开始新的一段工作
+        self.end_break(BreakEnd::Postponed, now);
+      }
+      EventKind::Escaped {
+        method,
+        reason,
+        skipped_secs,
+      } => self.end_break(
+        BreakEnd::Escaped {
+          method,
+          reason,
+          skipped_secs,
+        },
+        now,
+      ),
+      EventKind::BreakDeferred { process } => {
+        if let Some(reason) = status.deferred_by {
+          self.write(HistoryRecord::Deferred { reason, process });
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+/// 打开历史记录，按保留天数清理过期记录，并开始记录计时器事件
+pub fn setup_history(app: &mut App, bus: &CounterBus, retention_days: u32) -> HistoryState {
+  let dir = app
+    .path()
+    .app_data_dir()
+    .expect("failed to resolve app data dir");
+  let history = Arc::new(History::new(dir));
+  if retention_days > 0 {
+    let before = now_ms().saturating_sub(retention_days as u64 * 24 * 3600 * 1000);
+    if let Err(e) = history.prune(before) {
+      eprintln!("failed to prune history: {}", e);
+    }
+  }
+  app.manage(history.clone());
+
+  let mut tracker = Tracker {
+    history: history.clone(),
+    ongoing: None,
+  };
+  tracker.start_work(&bus.status(), now_ms());
+  let events = bus.subscribe();
+  std::thread::spawn(move || {
+    for event in events {
+      tracker.handle(event.kind, event.status);
+    }
+  });
+  history
+}
@@ -0,0 +1,235 @@
+//! 用户空闲时长检测。
+//!
+//! 计时器通过 [`IdleSource`] 获取键盘鼠标最近一次输入距今的秒数，
+//! 各平台的实现都放在这里，测试时可以替换成假的实现。
+
+pub trait IdleSource {
+  /// 用户已空闲的秒数，无法获取时返回 None
+  fn idle_secs(&mut self) -> Option<u32>;
+}
+
+/// 不支持空闲检测的平台使用，总是返回 None
+#[cfg(not(any(target_os = "linux", windows)))]
+pub struct NoIdleSource;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+impl IdleSource for NoIdleSource {
+  fn idle_secs(&mut self) -> Option<u32> {
+    None
+  }
+}
+
+/// 通过 X11 screensaver 扩展读取空闲时长
+#[cfg(target_os = "linux")]
+pub struct X11IdleSource {
+  conn: x11rb::rust_connection::RustConnection,
+  root: u32,
+}
+
+#[cfg(target_os = "linux")]
+impl X11IdleSource {
+  pub fn connect() -> Option<Self> {
+    use x11rb::{connection::Connection, protocol::screensaver::ConnectionExt};
+
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots.get(screen)?.root;
+    conn.screensaver_query_version(1, 1).ok()?.reply().ok()?;
+    Some(Self { conn, root })
+  }
+}
+
+#[cfg(target_os = "linux")]
+impl IdleSource for X11IdleSource {
+  fn idle_secs(&mut self) -> Option<u32> {
+    use x11rb::protocol::screensaver::ConnectionExt;
+
+    let info = self
+      .conn
+      .screensaver_query_info(self.root)
+      .ok()?
+      .reply()
+      .ok()?;
+    Some(info.ms_since_user_input / 1000)
+  }
+}
+
+/// 通过 logind 的 IdleHint 读取空闲时长，用于 Wayland 等没有 X11 的会话
+#[cfg(target_os = "linux")]
+pub struct LogindIdleSource;
+
+#[cfg(target_os = "linux")]
+impl IdleSource for LogindIdleSource {
+  fn idle_secs(&mut self) -> Option<u32> {
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".into());
+    let output = std::process::Command::new("loginctl")
+      .args([
+        "show-session",
+        &session,
+        "-p",
+        "IdleHint",
+        "-p",
+        "IdleSinceHint",
+      ])
+      .output()
+      .ok()?;
+    let output = String::from_utf8(output.stdout).ok()?;
+
+    let mut idle_hint = false;
+    let mut idle_since_us = 0u64;
+    for line in output.lines() {
+      match line.split_once('=') {
+        Some(("IdleHint", v)) => idle_hint = v == "yes",
+        Some(("IdleSinceHint", v)) => idle_since_us = v.parse().ok()?,
+        _ => {}
+      }
+    }
+    if !idle_hint || idle_since_us == 0 {
+      return Some(0);
+    }
+    let now_us = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .ok()?
+      .as_micros() as u64;
+    Some((now_us.saturating_sub(idle_since_us) / 1_000_000) as u32)
+  }
+}
+
+/// 通过 GetLastInputInfo 读取空闲时长
+#[cfg(windows)]
+pub struct WindowsIdleSource;
+
+#[cfg(windows)]
+impl IdleSource for WindowsIdleSource {
+  fn idle_secs(&mut self) -> Option<u32> {
+    use winapi::um::{
+      sysinfoapi::GetTickCount,
+      winuser::{GetLastInputInfo, LASTINPUTINFO},
+    };
+
+    let mut info = LASTINPUTINFO {
+      cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+      dwTime: 0,
+    };
+    unsafe {
+      if GetLastInputInfo(&mut info) == 0 {
+        return None;
+      }
+      Some(GetTickCount().wrapping_sub(info.dwTime) / 1000)
+    }
+  }
+}
+
+/// 返回当前平台可用的空闲检测实现
+pub fn system_idle_source() -> Box<dyn IdleSource + Send> {
+  #[cfg(target_os = "linux")]
+  {
+    if let Some(source) = X11IdleSource::connect() {
+      return Box::new(source);
+    }
+    Box::new(LogindIdleSource)
+  }
+  #[cfg(windows)]
+  {
+    Box::new(WindowsIdleSource)
+  }
+  #[cfg(not(any(target_os = "linux", windows)))]
+  {
+    Box::new(NoIdleSource)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::VecDeque;
+
+  use super::*;
+  use crate::timer::{FakeClock, Timer, TimerConfig, Transition};
+
+  /// 按顺序返回预先设定的空闲秒数
+  struct ScriptedIdleSource(VecDeque<u32>);
+
+  impl IdleSource for ScriptedIdleSource {
+    fn idle_secs(&mut self) -> Option<u32> {
+      self.0.pop_front()
+    }
+  }
+
+  const POLL_SECS: u32 = 5;
+
+  fn timer() -> (Timer<FakeClock>, FakeClock) {
+    let config = TimerConfig {
+      work_secs: 1800,
+      ..TimerConfig::default()
+    };
+    let clock = FakeClock::new(1_704_110_400_000);
+    (Timer::new(config, clock.clone()), clock)
+  }
+
+  /// 用户持续操作 `active` 秒，然后离开 `away` 秒再回来
+  fn script(active: u32, away: u32) -> ScriptedIdleSource {
+    let active = (0..active / POLL_SECS).map(|_| 0);
+    let away = (1..=away / POLL_SECS).map(|i| i * POLL_SECS);
+    ScriptedIdleSource(active.chain(away).chain([0]).collect())
+  }
+
+  /// 和界面层一样每隔一段时间 tick 并上报空闲时长，返回出现过的阶段切换
+  fn drive(
+    timer: &mut Timer<FakeClock>,
+    clock: &FakeClock,
+    source: &mut dyn IdleSource,
+  ) -> Vec<Transition> {
+    let mut transitions = Vec::new();
+    while let Some(secs) = source.idle_secs() {
+      clock.advance(POLL_SECS as u64 * 1000);
+      transitions.extend(timer.tick());
+      transitions.extend(timer.report_idle(secs));
+    }
+    transitions
+  }
+
+  #[test]
+  fn short_idle_is_not_counted_as_work() {
+    let (mut timer, clock) = timer();
+    let transitions = drive(&mut timer, &clock, &mut script(300, 90));
+    assert!(transitions.is_empty());
+    assert!(!timer.is_idle());
+    assert_eq!(timer.cycle(), 1);
+    assert_eq!(timer.elapsed_secs(), 300);
+  }
+
+  #[test]
+  fn idle_as_long_as_rest_credits_a_break() {
+    let (mut timer, clock) = timer();
+    let transitions = drive(&mut timer, &clock, &mut script(300, 120));
+    assert_eq!(transitions, [Transition::BreakCredited]);
+    assert_eq!(timer.cycle(), 2);
+    assert_eq!(timer.elapsed_secs(), 0);
+    // 离开的时间不算在这段工作里
+    assert_eq!(timer.last_work().worked_secs, 300);
+  }
+
+  #[test]
+  fn long_idle_credits_only_once() {
+    let (mut timer, clock) = timer();
+    let transitions = drive(&mut timer, &clock, &mut script(300, 600));
+    assert_eq!(transitions, [Transition::BreakCredited]);
+    assert_eq!(timer.cycle(), 2);
+    // 回来之后重新开始计时，离开的时间不算工作
+    assert_eq!(timer.elapsed_secs(), 0);
+  }
+
+  #[test]
+  fn idle_is_not_counted_towards_micro_breaks() {
+    let (mut timer, clock) = timer();
+    let mut config = *timer.config();
+    config.micro_break_every_secs = 600;
+    timer.apply_config(config);
+    let transitions = drive(&mut timer, &clock, &mut script(500, 100));
+    assert!(transitions.is_empty());
+
+    let transitions = drive(&mut timer, &clock, &mut script(90, 0));
+    assert!(transitions.is_empty());
+    let transitions = drive(&mut timer, &clock, &mut script(10, 0));
+    assert_eq!(transitions, [Transition::MicroBreakStart]);
+  }
+}
@@ -1,29 +1,83 @@
 mod chinese_font;
+mod cli;
 mod constant;
+mod display;
+mod escape;
+mod export;
+mod fullscreen;
+mod history;
+mod idle;
+mod message;
+mod notify;
+mod process;
+mod progress_icon;
+mod schedule;
 mod settings;
+mod shortcut;
+mod stats;
+mod timer;
 mod tray;
 mod window_counter;
 mod window_main;
 
-use std::sync::Arc;
-use std::sync::atomic::AtomicU8;
-
 use tauri::Manager;
 
+use crate::cli::{
+  CliCommand, check_control, parse_args, print_status, print_usage, setup_status_file,
+};
+use crate::export::tauri_export_history;
+use crate::history::setup_history;
+use crate::message::{CounterBus, CounterBusState, forward_events};
+use crate::notify::setup_notifications;
 use crate::settings::{setup_settings, tauri_refresh_settings};
+use crate::shortcut::{setup_shortcuts, tauri_get_shortcut_conflicts};
+use crate::stats::{tauri_get_daily_stats, tauri_get_streaks, tauri_get_weekly_stats};
 use crate::tray::setup_tray;
 use crate::window_counter::start_counter_app;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+  let context = tauri::generate_context!();
+  let cli_command = match parse_args(std::env::args()) {
+    Ok(command) => command,
+    Err(e) => std::process::exit(print_usage(Some(&e))),
+  };
+  match cli_command {
+    Some(CliCommand::Help) => std::process::exit(print_usage(None)),
+    // 状态从快照文件读取，不需要转发给正在运行的实例
+    Some(CliCommand::Status { json }) => {
+      std::process::exit(print_status(&context.config().identifier, json))
+    }
+    // 控制指令只转发给正在运行的实例，不会启动界面
+    Some(command) => {
+      if let Some(code) = check_control(&context.config().identifier, &command) {
+        std::process::exit(code);
+      }
+    }
+    None => {}
+  }
+
   tauri::Builder::default()
     .plugin(tauri_plugin_os::init())
+    .plugin(tauri_plugin_notification::init())
     .plugin(tauri_plugin_store::Builder::new().build())
     .plugin(tauri_plugin_autostart::init(
       tauri_plugin_autostart::MacosLauncher::LaunchAgent,
       None,
     ))
-    .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+    .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+    .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+      // 命令行指令在正在运行的实例中执行，其它情况打开主窗口
+      if let Ok(Some(command)) = parse_args(args) {
+        let bus = app.state::<CounterBusState>();
+        // 和托盘一样，严格模式下的休息中等不会生效的指令直接忽略
+        if command.rejection(&bus.status()).is_none()
+          && let Some(command) = command.command()
+        {
+          let _ = bus.send(command);
+        }
+        return;
+      }
       // let win = app.get_webview_window("main").expect("no main window");
       // win.show().unwrap();
       // let _ = win.set_focus();
@@ -33,17 +87,29 @@ pub fn run() {
       let _ = win.show();
       let _ = win.set_focus();
     }))
-    .invoke_handler(tauri::generate_handler![tauri_refresh_settings])
-    .setup(|app| {
-      setup_tray(app);
+    .invoke_handler(tauri::generate_handler![
+      tauri_refresh_settings,
+      tauri_get_daily_stats,
+      tauri_get_weekly_stats,
+      tauri_get_streaks,
+      tauri_export_history,
+      tauri_get_shortcut_conflicts
+    ])
+    .setup(move |app| {
       let settings = setup_settings(app);
-      let event_signal = Arc::new(AtomicU8::new(0));
-      app.manage(event_signal.clone());
+      let (bus, commands) = CounterBus::new();
+      app.manage(bus.clone());
+      setup_tray(app, &bus);
+      forward_events(app.handle(), &bus);
+      setup_notifications(app.handle(), &bus);
+      setup_shortcuts(app, &settings.shortcuts);
+      let history = setup_history(app, &bus, settings.history_retention_days);
+      setup_status_file(app, &bus);
 
-      start_counter_app(&settings, event_signal);
+      start_counter_app(&settings, history.postponed_today(), bus, commands);
 
       Ok(())
     })
-    .run(tauri::generate_context!())
+    .run(context)
     .expect("error while running tauri application");
 }
@@ -1,7 +1,217 @@
-#[derive(Clone, Copy)]
-pub enum Message {
-  Close,
-  Update(u32),
-  CounterEnd,
-  BlockerEnd,
+use std::sync::{Arc, Mutex, mpsc};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::{
+  settings::{EscapeMethod, Settings},
+  timer::{DeferReason, Phase, WorkSpan},
+};
+
+/// 前端监听的计时器事件名
+pub const COUNTER_EVENT: &'static str = "counter-event";
+
+/// 发给计时器的指令
+#[derive(Debug, Clone)]
+pub enum Command {
+  /// 暂停工作计时，带秒数时到点自动继续
+  Pause(Option<u32>),
+  /// 暂停到下一个工作日开始工作时
+  PauseUntilNextWorkday,
+  Resume,
+  Reset,
+  /// 推迟即将开始或正在进行的休息
+  Postpone,
+  /// 立即开始休息
+  BreakNow,
+  ApplySettings(Settings),
+}
+
+impl Command {
+  /// 指令在计时器当前状态下不会生效的原因，托盘菜单、快捷键和命令行共用这套规则
+  pub fn rejection(&self, status: &CounterStatus) -> Option<&'static str> {
+    let working = matches!(status.phase, Phase::Counting);
+    match self {
+      Command::ApplySettings(_) => None,
+      _ if status.strict_break => Some("严格模式下休息期间不接受指令"),
+      Command::Pause(_) | Command::PauseUntilNextWorkday | Command::BreakNow
+        if !working || status.off_hours =>
+      {
+        Some("现在不在工作时间内")
+      }
+      Command::Reset if !working => Some("休息期间不能重新开始"),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EventKind {
+  PhaseChanged,
+  Paused,
+  Resumed,
+  Reset,
+  SettingsApplied,
+  /// 离开（休眠）足够久，自动算作完成一次休息
+  BreakCredited,
+  /// 即将开始休息
+  BreakWarning,
+  MicroBreakStarted,
+  MicroBreakEnded,
+  Postponed,
+  /// 推迟次数已用完或当前不能推迟
+  PostponeRejected,
+  WorkHoursStarted,
+  WorkHoursEnded,
+  /// 工作时长已到，但暂缓进入休息
+  BreakDeferred {
+    /// 因为程序运行而暂缓时的程序名
+    process: Option<String>,
+  },
+  /// 紧急退出了休息
+  #[serde(rename_all = "camelCase")]
+  Escaped {
+    method: EscapeMethod,
+    reason: Option<String>,
+    /// 跳过的休息秒数
+    skipped_secs: u32,
+  },
+}
+
+/// 计时器当前状态的快照
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CounterStatus {
+  pub phase: Phase,
+  pub paused: bool,
+  /// 定时暂停还剩的秒数
+  pub pause_remaining_secs: Option<u32>,
+  /// 暂停的最长秒数，None 表示不限制
+  pub pause_limit_secs: Option<u32>,
+  /// 距离下一个工作日开始工作的秒数
+  pub next_workday_secs: Option<u32>,
+  /// 当前是第几轮工作
+  pub cycle: u32,
+  /// 本轮的休息是否为长休息
+  pub long_break: bool,
+  /// 是否处于护眼小休息中
+  pub micro_break: bool,
+  /// 是否处于休息前的预警阶段
+  pub warning: bool,
+  /// 本轮还能推迟的次数
+  pub postpones_left: u32,
+  /// 现在是否可以推迟
+  pub can_postpone: bool,
+  /// 是否处于严格模式下的休息中
+  pub strict_break: bool,
+  /// 是否处于工作时间之外
+  pub off_hours: bool,
+  /// 用户是否离开中，离开期间工作时间不累计
+  pub idle: bool,
+  /// 工作时长已到、正在暂缓休息的原因
+  pub deferred_by: Option<DeferReason>,
+  pub elapsed_secs: u32,
+  pub remaining_secs: u32,
+  /// 上一段结束的工作实际工作了多久
+  pub last_work: WorkSpan,
+}
+
+impl Default for CounterStatus {
+  fn default() -> Self {
+    Self {
+      phase: Phase::Counting,
+      paused: false,
+      pause_remaining_secs: None,
+      pause_limit_secs: None,
+      next_workday_secs: None,
+      cycle: 1,
+      long_break: false,
+      micro_break: false,
+      warning: false,
+      postpones_left: 0,
+      can_postpone: false,
+      strict_break: false,
+      off_hours: false,
+      idle: false,
+      deferred_by: None,
+      elapsed_secs: 0,
+      remaining_secs: 0,
+      last_work: WorkSpan::default(),
+    }
+  }
+}
+
+/// 计时器广播出来的状态变化
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+  pub kind: EventKind,
+  pub status: CounterStatus,
+}
+
+/// 托盘、主窗口与计时器之间的指令/事件总线。
+///
+/// 指令走 mpsc 队列，不会互相覆盖；事件会复制给每个订阅者。
+pub struct CounterBus {
+  commands: mpsc::Sender<Command>,
+  subscribers: Mutex<Vec<mpsc::Sender<Event>>>,
+  status: Mutex<CounterStatus>,
+}
+
+pub type CounterBusState = Arc<CounterBus>;
+
+impl CounterBus {
+  /// 返回总线以及计时器端使用的指令接收端
+  pub fn new() -> (CounterBusState, mpsc::Receiver<Command>) {
+    let (tx, rx) = mpsc::channel();
+    let bus = Arc::new(Self {
+      commands: tx,
+      subscribers: Mutex::new(Vec::new()),
+      status: Mutex::new(CounterStatus::default()),
+    });
+    (bus, rx)
+  }
+
+  pub fn send(&self, command: Command) -> Result<(), String> {
+    self
+      .commands
+      .send(command)
+      .map_err(|_| "计时器未启动".to_string())
+  }
+
+  pub fn subscribe(&self) -> mpsc::Receiver<Event> {
+    let (tx, rx) = mpsc::channel();
+    self.subscribers.lock().unwrap().push(tx);
+    rx
+  }
+
+  /// 广播事件，同时更新状态快照。已经断开的订阅者会被移除。
+  pub fn publish(&self, event: Event) {
+    self.update_status(event.status);
+    self
+      .subscribers
+      .lock()
+      .unwrap()
+      .retain(|tx| tx.send(event.clone()).is_ok());
+  }
+
+  pub fn update_status(&self, status: CounterStatus) {
+    *self.status.lock().unwrap() = status;
+  }
+
+  pub fn status(&self) -> CounterStatus {
+    *self.status.lock().unwrap()
+  }
+}
+
+/// 把计时器事件转发给前端页面
+pub fn forward_events<R: Runtime>(app: &AppHandle<R>, bus: &CounterBus) {
+  let app = app.clone();
+  let events = bus.subscribe();
+  std::thread::spawn(move || {
+    for event in events {
+      let _ = app.emit(COUNTER_EVENT, event);
+    }
+  });
 }
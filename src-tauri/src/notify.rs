@@ -0,0 +1,46 @@
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::{
+  constant::APP_TITLE,
+  message::{CounterBus, EventKind},
+  timer::DeferReason,
+};
+
+/// 监听计时器事件，在需要时弹出系统通知
+pub fn setup_notifications<R: Runtime>(app: &AppHandle<R>, bus: &CounterBus) {
+  let app = app.clone();
+  let events = bus.subscribe();
+  std::thread::spawn(move || {
+    for event in events {
+      let body = match event.kind {
+        EventKind::BreakWarning => format!(
+          "{} 秒后开始休息，请收尾手头的工作~",
+          event.status.remaining_secs
+        ),
+        EventKind::PostponeRejected if event.status.strict_break => {
+          "严格模式下休息期间不能推迟".to_string()
+        }
+        EventKind::PostponeRejected if event.status.postpones_left == 0 => {
+          "推迟次数已用完，该休息了~".to_string()
+        }
+        EventKind::PostponeRejected => "距离休息还早，无需推迟".to_string(),
+        EventKind::BreakDeferred { process } => match event.status.deferred_by {
+          Some(DeferReason::Fullscreen) => "检测到全屏应用，休息将在退出全屏后开始".to_string(),
+          Some(DeferReason::Process) => format!(
+            "{} 正在运行，休息将在它退出后开始",
+            process.unwrap_or_default()
+          ),
+          None => continue,
+        },
+        _ => continue,
+      };
+      let _ = app
+        .notification()
+        .builder()
+        .title(APP_TITLE)
+        .body(body)
+        .show();
+    }
+  });
+}
@@ -0,0 +1,136 @@
+//! 正在运行的进程检测。
+//!
+//! 设置中列出的程序（例如 zoom、obs）运行时计时器会暂缓休息，
+//! 各平台列出进程名的实现都放在这里，通过 [`ProcessSource`] 使用。
+
+pub trait ProcessSource {
+  /// 当前正在运行的进程名，无法获取时返回空列表
+  fn process_names(&mut self) -> Vec<String>;
+}
+
+/// 统一进程名的格式：忽略大小写和 Windows 下的 .exe 后缀
+fn normalize(name: &str) -> String {
+  let name = name.trim().to_lowercase();
+  match name.strip_suffix(".exe") {
+    Some(stem) => stem.to_string(),
+    None => name,
+  }
+}
+
+/// 返回 `names` 中第一个正在运行的程序
+pub fn find_running(source: &mut dyn ProcessSource, names: &[String]) -> Option<String> {
+  if names.is_empty() {
+    return None;
+  }
+  let running: Vec<String> = source
+    .process_names()
+    .into_iter()
+    .map(|n| normalize(&n))
+    .collect();
+  names
+    .iter()
+    .find(|name| running.contains(&normalize(name)))
+    .cloned()
+}
+
+/// 不支持进程检测的平台使用，总是返回空列表
+#[cfg(not(any(target_os = "linux", windows)))]
+pub struct NoProcessSource;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+impl ProcessSource for NoProcessSource {
+  fn process_names(&mut self) -> Vec<String> {
+    Vec::new()
+  }
+}
+
+/// 扫描 /proc 读取进程名
+#[cfg(target_os = "linux")]
+pub struct ProcfsProcessSource;
+
+#[cfg(target_os = "linux")]
+impl ProcessSource for ProcfsProcessSource {
+  fn process_names(&mut self) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+      return Vec::new();
+    };
+    let mut names = Vec::new();
+    for entry in entries.flatten() {
+      let path = entry.path();
+      if !entry
+        .file_name()
+        .to_string_lossy()
+        .bytes()
+        .all(|b| b.is_ascii_digit())
+      {
+        continue;
+      }
+      // comm 最多只有 15 个字符，再补充命令行第一个参数的文件名
+      if let Ok(comm) = std::fs::read_to_string(path.join("comm")) {
+        names.push(comm.trim_end().to_string());
+      }
+      if let Ok(cmdline) = std::fs::read(path.join("cmdline"))
+        && let Some(arg0) = cmdline.split(|b| *b == 0).next()
+        && let Some(file_name) = std::path::Path::new(&*String::from_utf8_lossy(arg0)).file_name()
+      {
+        names.push(file_name.to_string_lossy().into_owned());
+      }
+    }
+    names
+  }
+}
+
+/// 通过 CreateToolhelp32Snapshot 列出进程
+#[cfg(windows)]
+pub struct WindowsProcessSource;
+
+#[cfg(windows)]
+impl ProcessSource for WindowsProcessSource {
+  fn process_names(&mut self) -> Vec<String> {
+    use winapi::um::{
+      handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+      tlhelp32::{
+        CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW,
+        TH32CS_SNAPPROCESS,
+      },
+    };
+
+    let mut names = Vec::new();
+    unsafe {
+      let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+      if snapshot == INVALID_HANDLE_VALUE {
+        return names;
+      }
+      let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+      entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+      let mut ok = Process32FirstW(snapshot, &mut entry);
+      while ok != 0 {
+        let len = entry
+          .szExeFile
+          .iter()
+          .position(|c| *c == 0)
+          .unwrap_or(entry.szExeFile.len());
+        names.push(String::from_utf16_lossy(&entry.szExeFile[..len]));
+        ok = Process32NextW(snapshot, &mut entry);
+      }
+      CloseHandle(snapshot);
+    }
+    names
+  }
+}
+
+/// 返回当前平台可用的进程检测实现
+pub fn system_process_source() -> Box<dyn ProcessSource + Send> {
+  #[cfg(target_os = "linux")]
+  {
+    Box::new(ProcfsProcessSource)
+  }
+  #[cfg(windows)]
+  {
+    Box::new(WindowsProcessSource)
+  }
+  #[cfg(not(any(target_os = "linux", windows)))]
+  {
+    Box::new(NoProcessSource)
+  }
+}
@@ -0,0 +1,92 @@
+//! 运行时绘制的托盘图标：一个随工作或休息进度填满的圆环，颜色表示当前状态。
+
+use std::f32::consts::TAU;
+
+use tauri::image::Image;
+
+use crate::{message::CounterStatus, timer::Phase};
+
+const ICON_SIZE: u32 = 64;
+const RING_OUTER_RADIUS: f32 = 30.0;
+const RING_INNER_RADIUS: f32 = 19.0;
+/// 每个像素的超采样次数（每个方向）
+const SUPERSAMPLE: u32 = 4;
+/// 圆环分成的格数，进度跨过一格时才重新绘制
+const PROGRESS_STEPS: u32 = 60;
+/// 未填充部分的透明度
+const TRACK_ALPHA: f32 = 0.3;
+
+const COLOR_WORKING: [u8; 3] = [0x22, 0xc5, 0x5e];
+const COLOR_WARNING: [u8; 3] = [0xf5, 0x9e, 0x0b];
+const COLOR_PAUSED: [u8; 3] = [0x9c, 0xa3, 0xaf];
+const COLOR_BREAK: [u8; 3] = [0x3b, 0x82, 0xf6];
+const COLOR_OFF_HOURS: [u8; 3] = [0x6b, 0x72, 0x80];
+
+/// 托盘图标的外观，相同时不需要重新绘制
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressIcon {
+  color: [u8; 3],
+  /// 已填充的格数，0..=PROGRESS_STEPS
+  filled: u32,
+}
+
+impl ProgressIcon {
+  pub fn from_status(status: &CounterStatus) -> Self {
+    let total = status.elapsed_secs + status.remaining_secs;
+    let progress = if total == 0 {
+      1.0
+    } else {
+      (status.elapsed_secs as f32 / total as f32).min(1.0)
+    };
+    let (color, progress) = if status.off_hours {
+      (COLOR_OFF_HOURS, 0.0)
+    } else {
+      match status.phase {
+        Phase::Counting if status.paused => (COLOR_PAUSED, progress),
+        Phase::Counting if status.warning || status.deferred_by.is_some() => {
+          (COLOR_WARNING, progress)
+        }
+        Phase::Counting => (COLOR_WORKING, progress),
+        Phase::Blocking => (COLOR_BREAK, progress),
+        Phase::BlockEnd => (COLOR_BREAK, 1.0),
+      }
+    };
+    Self {
+      color,
+      filled: (progress * PROGRESS_STEPS as f32).floor() as u32,
+    }
+  }
+
+  /// 绘制成 RGBA 图标，从十二点方向顺时针填充
+  pub fn render(&self) -> Image<'static> {
+    let filled_angle = self.filled as f32 / PROGRESS_STEPS as f32 * TAU;
+    let center = ICON_SIZE as f32 / 2.0;
+    let samples = (SUPERSAMPLE * SUPERSAMPLE) as f32;
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+      for x in 0..ICON_SIZE {
+        let mut alpha = 0.0;
+        for sy in 0..SUPERSAMPLE {
+          for sx in 0..SUPERSAMPLE {
+            let dx = x as f32 + (sx as f32 + 0.5) / SUPERSAMPLE as f32 - center;
+            let dy = y as f32 + (sy as f32 + 0.5) / SUPERSAMPLE as f32 - center;
+            let distance = (dx * dx + dy * dy).sqrt();
+            if !(RING_INNER_RADIUS..=RING_OUTER_RADIUS).contains(&distance) {
+              continue;
+            }
+            // 以十二点方向为 0，顺时针增加
+            let angle = dx.atan2(-dy).rem_euclid(TAU);
+            alpha += if angle < filled_angle {
+              1.0
+            } else {
+              TRACK_ALPHA
+            };
+          }
+        }
+        let [r, g, b] = self.color;
+        rgba.extend_from_slice(&[r, g, b, (alpha / samples * 255.0).round() as u8]);
+      }
+    }
+    Image::new_owned(rgba, ICON_SIZE, ICON_SIZE)
+  }
+}
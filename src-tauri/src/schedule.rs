@@ -0,0 +1,91 @@
+//! 工作时间表，工作时间之外计时器休眠。
+
+use chrono::{Datelike, Days, Local, NaiveDate, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// 一天中的工作时间段，用从零点开始的分钟数表示，不跨天
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkWindow {
+  pub start_minute: u32,
+  pub end_minute: u32,
+}
+
+impl WorkWindow {
+  fn contains(&self, minute: u32) -> bool {
+    minute >= self.start_minute && minute < self.end_minute
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+  /// 未启用时全天都是工作时间
+  pub enabled: bool,
+  /// 周一到周日的工作时间段，None 表示当天不工作
+  pub days: [Option<WorkWindow>; 7],
+}
+
+impl Default for Schedule {
+  fn default() -> Self {
+    // 周一到周五 09:00 - 18:30
+    let workday = Some(WorkWindow {
+      start_minute: 9 * 60,
+      end_minute: 18 * 60 + 30,
+    });
+    Self {
+      enabled: false,
+      days: [workday, workday, workday, workday, workday, None, None],
+    }
+  }
+}
+
+impl Schedule {
+  /// 给定的系统时间（unix 毫秒）是否处于工作时间内
+  pub fn is_working_at(&self, wall_ms: u64) -> bool {
+    if !self.enabled {
+      return true;
+    }
+    let Some(time) = Local.timestamp_millis_opt(wall_ms as i64).single() else {
+      return true;
+    };
+    let minute = time.hour() * 60 + time.minute();
+    self.days[time.weekday().num_days_from_monday() as usize].is_some_and(|w| w.contains(minute))
+  }
+
+  /// 按每周的设置判断这一天是否为工作日，不考虑时间表是否启用
+  pub fn is_workday(&self, day: NaiveDate) -> bool {
+    self.days[day.weekday().num_days_from_monday() as usize].is_some()
+  }
+
+  /// 下一个工作日开始工作的时间（unix 毫秒），今天的工作还没开始时就是今天，未启用时为明天零点
+  pub fn next_workday_start(&self, wall_ms: u64) -> Option<u64> {
+    let today = Local
+      .timestamp_millis_opt(wall_ms as i64)
+      .single()?
+      .date_naive();
+    (0..=7).find_map(|i| {
+      let day = today.checked_add_days(Days::new(i))?;
+      let start_minute = if self.enabled {
+        self.days[day.weekday().num_days_from_monday() as usize]?.start_minute
+      } else {
+        0
+      };
+      let start = day.and_hms_opt(start_minute / 60, start_minute % 60, 0)?;
+      Local
+        .from_local_datetime(&start)
+        .earliest()
+        .map(|t| t.timestamp_millis() as u64)
+        .filter(|&at| at > wall_ms)
+    })
+  }
+
+  pub fn validate(&self) -> Result<(), String> {
+    for w in self.days.iter().flatten() {
+      if w.end_minute > 24 * 60 || w.start_minute >= w.end_minute {
+        return Err("工作时间的结束时间必须晚于开始时间".into());
+      }
+    }
+    Ok(())
+  }
+}
@@ -1,8 +1,13 @@
 use serde::{Deserialize, Serialize};
-use tauri::{App, AppHandle, Runtime};
+use tauri::{App, AppHandle, Manager, Runtime};
 use tauri_plugin_store::StoreExt;
 
-use crate::constant::{STORE_DATA_PATH, STORE_SETTINGS_KEY};
+use crate::{
+  constant::{STORE_DATA_PATH, STORE_SETTINGS_KEY},
+  message::{Command, CounterBusState},
+  schedule::Schedule,
+  shortcut::{ShortcutConflict, ShortcutSettings, apply_shortcuts},
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -11,13 +16,110 @@ pub struct Settings {
   pub work_secs: u32,
   #[serde(default = "default_rest_secs")]
   pub rest_secs: u32,
+  #[serde(default = "default_long_rest_secs")]
+  pub long_rest_secs: u32,
+  /// 每隔多少轮工作进行一次长休息，0 表示不启用
+  #[serde(default)]
+  pub long_rest_every: u32,
+  /// 护眼小休息的间隔，0 表示不启用
+  #[serde(default)]
+  pub micro_break_every_secs: u32,
+  #[serde(default = "default_micro_break_secs")]
+  pub micro_break_secs: u32,
+  /// 休息开始前多少秒发出预警，0 表示不预警
+  #[serde(default = "default_warning_secs")]
+  pub warning_secs: u32,
+  #[serde(default = "default_postpone_secs")]
+  pub postpone_secs: u32,
+  /// 每轮工作最多推迟几次，0 表示不允许推迟
+  #[serde(default = "default_postpone_per_cycle")]
+  pub postpone_per_cycle: u32,
+  #[serde(default = "default_postpone_per_day")]
+  pub postpone_per_day: u32,
+  #[serde(default)]
+  pub escape_method: EscapeMethod,
+  /// 连按 Escape 紧急退出需要的次数
   #[serde(default = "default_escape_count")]
   pub escape_count: u32,
+  /// 按住 Escape 紧急退出需要的秒数
+  #[serde(default = "default_escape_hold_secs")]
+  pub escape_hold_secs: u32,
+  /// 输入确认语句紧急退出时需要输入的内容
+  #[serde(default = "default_escape_phrase")]
+  pub escape_phrase: String,
+  /// 严格模式：休息期间不能紧急退出、推迟，托盘的暂停/重置/退出不可用
+  #[serde(default)]
+  pub strict_mode: bool,
+  /// 休息期间不允许关闭严格模式，关闭操作在休息结束后才生效
+  #[serde(default)]
+  pub strict_lock_during_break: bool,
+  #[serde(default)]
+  pub sleep_policy: SleepPolicy,
+  #[serde(default = "default_idle_threshold_secs")]
+  pub idle_threshold_secs: u32,
+  /// 暂停的最长秒数，到点自动继续，0 表示不限制
+  #[serde(default)]
+  pub max_pause_secs: u32,
+  /// 全局快捷键
+  #[serde(default)]
+  pub shortcuts: ShortcutSettings,
+  /// 工作时间表，工作时间之外计时器休眠
+  #[serde(default)]
+  pub schedule: Schedule,
+  /// 主屏幕有全屏窗口（演示、视频会议）时暂缓休息
+  #[serde(default)]
+  pub fullscreen_dnd: bool,
+  /// 暂缓休息的最长秒数
+  #[serde(default = "default_defer_max_secs")]
+  pub defer_max_secs: u32,
+  /// 这些程序运行时暂缓休息，例如 zoom、obs
+  #[serde(default)]
+  pub defer_processes: Vec<String>,
+  /// 历史记录保留的天数，0 表示永久保留，启动时清理
+  #[serde(default = "default_history_retention_days")]
+  pub history_retention_days: u32,
+}
+
+/// 休息期间紧急退出的方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EscapeMethod {
+  /// 连按 Escape `escape_count` 次
+  #[default]
+  Repeat,
+  /// 按住 Escape `escape_hold_secs` 秒
+  Hold,
+  /// 输入确认语句
+  Phrase,
+  /// 答对一道算术题
+  Arithmetic,
+}
+
+/// 工作期间系统休眠后的处理方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SleepPolicy {
+  /// 休眠时长达到休息时长时算作完成一次休息，否则只当作暂停
+  #[default]
+  Rest,
+  /// 休眠总是当作暂停
+  Pause,
 }
 
 pub const DEFAULT_WORK_SECS: u32 = 60 * 30;
 pub const DEFAULT_REST_SECS: u32 = 60 * 2;
+pub const DEFAULT_LONG_REST_SECS: u32 = 60 * 15;
+pub const DEFAULT_MICRO_BREAK_SECS: u32 = 20;
+pub const DEFAULT_WARNING_SECS: u32 = 60;
+pub const DEFAULT_POSTPONE_SECS: u32 = 60 * 5;
+pub const DEFAULT_POSTPONE_PER_CYCLE: u32 = 1;
+pub const DEFAULT_POSTPONE_PER_DAY: u32 = 3;
 pub const DEFAULT_ESCAPE_COUNT: u32 = 10;
+pub const DEFAULT_ESCAPE_HOLD_SECS: u32 = 5;
+pub const DEFAULT_ESCAPE_PHRASE: &'static str = "我确认现在必须跳过休息";
+pub const DEFAULT_IDLE_THRESHOLD_SECS: u32 = 60 * 2;
+pub const DEFAULT_DEFER_MAX_SECS: u32 = 60 * 15;
+pub const DEFAULT_HISTORY_RETENTION_DAYS: u32 = 365;
 
 #[inline]
 fn default_work_secs() -> u32 {
@@ -28,16 +130,82 @@ fn default_rest_secs() -> u32 {
   DEFAULT_REST_SECS
 }
 #[inline]
+fn default_long_rest_secs() -> u32 {
+  DEFAULT_LONG_REST_SECS
+}
+#[inline]
+fn default_micro_break_secs() -> u32 {
+  DEFAULT_MICRO_BREAK_SECS
+}
+#[inline]
+fn default_warning_secs() -> u32 {
+  DEFAULT_WARNING_SECS
+}
+#[inline]
+fn default_postpone_secs() -> u32 {
+  DEFAULT_POSTPONE_SECS
+}
+#[inline]
+fn default_postpone_per_cycle() -> u32 {
+  DEFAULT_POSTPONE_PER_CYCLE
+}
+#[inline]
+fn default_postpone_per_day() -> u32 {
+  DEFAULT_POSTPONE_PER_DAY
+}
+#[inline]
 fn default_escape_count() -> u32 {
   DEFAULT_ESCAPE_COUNT
 }
+#[inline]
+fn default_escape_hold_secs() -> u32 {
+  DEFAULT_ESCAPE_HOLD_SECS
+}
+#[inline]
+fn default_escape_phrase() -> String {
+  DEFAULT_ESCAPE_PHRASE.to_string()
+}
+#[inline]
+fn default_idle_threshold_secs() -> u32 {
+  DEFAULT_IDLE_THRESHOLD_SECS
+}
+#[inline]
+fn default_defer_max_secs() -> u32 {
+  DEFAULT_DEFER_MAX_SECS
+}
+#[inline]
+fn default_history_retention_days() -> u32 {
+  DEFAULT_HISTORY_RETENTION_DAYS
+}
 
 impl Default for Settings {
   fn default() -> Self {
     Settings {
       work_secs: DEFAULT_WORK_SECS,
       rest_secs: DEFAULT_REST_SECS,
+      long_rest_secs: DEFAULT_LONG_REST_SECS,
+      long_rest_every: 0,
+      micro_break_every_secs: 0,
+      micro_break_secs: DEFAULT_MICRO_BREAK_SECS,
+      warning_secs: DEFAULT_WARNING_SECS,
+      postpone_secs: DEFAULT_POSTPONE_SECS,
+      postpone_per_cycle: DEFAULT_POSTPONE_PER_CYCLE,
+      postpone_per_day: DEFAULT_POSTPONE_PER_DAY,
+      escape_method: EscapeMethod::default(),
       escape_count: DEFAULT_ESCAPE_COUNT,
+      escape_hold_secs: DEFAULT_ESCAPE_HOLD_SECS,
+      escape_phrase: default_escape_phrase(),
+      strict_mode: false,
+      strict_lock_during_break: false,
+      sleep_policy: SleepPolicy::default(),
+      idle_threshold_secs: DEFAULT_IDLE_THRESHOLD_SECS,
+      max_pause_secs: 0,
+      shortcuts: ShortcutSettings::default(),
+      schedule: Schedule::default(),
+      fullscreen_dnd: false,
+      defer_max_secs: DEFAULT_DEFER_MAX_SECS,
+      defer_processes: Vec::new(),
+      history_retention_days: DEFAULT_HISTORY_RETENTION_DAYS,
     }
   }
 }
@@ -46,7 +214,20 @@ pub fn setup_settings(app: &mut App) -> Settings {
   let store = app.store(STORE_DATA_PATH).unwrap();
 
   if let Some(settings) = store.get(STORE_SETTINGS_KEY) {
-    serde_json::from_value::<Settings>(settings).unwrap()
+    // 保存的配置无法解析或校验不通过时使用默认配置，界面保存合法配置后再生效
+    match serde_json::from_value::<Settings>(settings) {
+      Ok(settings) => match settings.validate() {
+        Ok(()) => settings,
+        Err(e) => {
+          eprintln!("invalid settings, use defaults: {}", e);
+          Settings::default()
+        }
+      },
+      Err(e) => {
+        eprintln!("failed to parse settings, use defaults: {}", e);
+        Settings::default()
+      }
+    }
   } else {
     let default_settings = Settings::default();
     store.set(
@@ -57,16 +238,73 @@ pub fn setup_settings(app: &mut App) -> Settings {
   }
 }
 
+impl Settings {
+  /// 校验配置是否可以被计时器使用
+  pub fn validate(&self) -> Result<(), String> {
+    if self.work_secs == 0 {
+      return Err("工作时长必须大于 0".into());
+    }
+    if self.rest_secs == 0 {
+      return Err("休息时长必须大于 0".into());
+    }
+    if self.long_rest_every > 0 && self.long_rest_secs == 0 {
+      return Err("长休息时长必须大于 0".into());
+    }
+    // 长休息比普通休息短时，离开一小会儿就会被当成长休息
+    if self.long_rest_every > 0 && self.long_rest_secs < self.rest_secs {
+      return Err("长休息时长不能短于休息时长".into());
+    }
+    if self.micro_break_every_secs > 0 && self.micro_break_secs == 0 {
+      return Err("护眼休息时长必须大于 0".into());
+    }
+    if self.postpone_per_cycle > 0 && self.postpone_secs == 0 {
+      return Err("推迟时长必须大于 0".into());
+    }
+    match self.escape_method {
+      EscapeMethod::Repeat if self.escape_count == 0 => {
+        return Err("紧急退出次数必须大于 0".into());
+      }
+      EscapeMethod::Hold if self.escape_hold_secs == 0 => {
+        return Err("按住 Escape 的时长必须大于 0".into());
+      }
+      EscapeMethod::Phrase if self.escape_phrase.trim().is_empty() => {
+        return Err("紧急退出的确认语句不能为空".into());
+      }
+      _ => {}
+    }
+    self.schedule.validate()?;
+    self.shortcuts.validate()
+  }
+}
+
+/// 读取 store 中保存的配置，不存在或无法解析时返回 None
+pub fn load_settings<R: Runtime>(app: &AppHandle<R>) -> Option<Settings> {
+  let settings = app.store(STORE_DATA_PATH).ok()?.get(STORE_SETTINGS_KEY)?;
+  serde_json::from_value(settings).ok()
+}
+
+/// 校验界面提交的配置，通过后写入 store 并应用到正在运行的计时器，返回注册失败的快捷键。
+/// 校验失败时 store 保持不变。快捷键冲突不影响其它配置生效，由界面在对应的快捷键上提示。
 #[tauri::command]
-pub async fn tauri_refresh_settings<R: Runtime>(app: AppHandle<R>) -> Result<bool, String> {
-  let store = app.store(STORE_DATA_PATH).unwrap();
-  let Some(settings) = store.get(STORE_SETTINGS_KEY) else {
-    return Ok(false);
-  };
+pub async fn tauri_refresh_settings<R: Runtime>(
+  app: AppHandle<R>,
+  settings: serde_json::Value,
+) -> Result<Vec<ShortcutConflict>, String> {
+  // 原样保存界面提交的内容，保留只有界面使用的字段（例如开机启动）
+  let parsed = serde_json::from_value::<Settings>(settings.clone())
+    .map_err(|e| format!("配置格式错误：{}", e))?;
+  parsed.validate()?;
 
-  let Ok(settings) = serde_json::from_value::<Settings>(settings) else {
-    return Ok(false);
+  let Some(bus) = app.try_state::<CounterBusState>() else {
+    return Err("计时器未启动".into());
   };
+  let store = app
+    .store(STORE_DATA_PATH)
+    .map_err(|e| format!("打开配置存储失败：{}", e))?;
+  store.set(STORE_SETTINGS_KEY, settings);
+  store.save().map_err(|e| format!("保存配置失败：{}", e))?;
 
-  Ok(true)
+  let conflicts = apply_shortcuts(&app, &parsed.shortcuts);
+  bus.send(Command::ApplySettings(parsed))?;
+  Ok(conflicts)
 }
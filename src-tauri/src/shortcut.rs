@@ -0,0 +1,154 @@
+//! 系统全局快捷键，触发和托盘菜单相同的指令，不会生效的指令（例如休息中暂停）直接忽略。
+
+use std::{str::FromStr, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::{App, AppHandle, Manager, Runtime, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::message::{Command, CounterBusState};
+
+/// 各操作绑定的快捷键，例如 `CommandOrControl+Alt+P`，空字符串表示不绑定
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutSettings {
+  #[serde(default)]
+  pub pause: String,
+  #[serde(default)]
+  pub resume: String,
+  #[serde(default)]
+  pub reset: String,
+  #[serde(default)]
+  pub break_now: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortcutAction {
+  Pause,
+  Resume,
+  Reset,
+  BreakNow,
+}
+
+impl ShortcutAction {
+  fn label(self) -> &'static str {
+    match self {
+      ShortcutAction::Pause => "暂停",
+      ShortcutAction::Resume => "继续",
+      ShortcutAction::Reset => "重置",
+      ShortcutAction::BreakNow => "立即休息",
+    }
+  }
+
+  fn command(self) -> Command {
+    match self {
+      ShortcutAction::Pause => Command::Pause(None),
+      ShortcutAction::Resume => Command::Resume,
+      ShortcutAction::Reset => Command::Reset,
+      ShortcutAction::BreakNow => Command::BreakNow,
+    }
+  }
+}
+
+impl ShortcutSettings {
+  fn bindings(&self) -> [(ShortcutAction, &str); 4] {
+    [
+      (ShortcutAction::Pause, self.pause.trim()),
+      (ShortcutAction::Resume, self.resume.trim()),
+      (ShortcutAction::Reset, self.reset.trim()),
+      (ShortcutAction::BreakNow, self.break_now.trim()),
+    ]
+  }
+
+  /// 校验快捷键的格式，以及是否有重复的绑定
+  pub fn validate(&self) -> Result<(), String> {
+    let mut parsed: Vec<(ShortcutAction, Shortcut)> = Vec::new();
+    for (action, accelerator) in self.bindings() {
+      if accelerator.is_empty() {
+        continue;
+      }
+      let shortcut = Shortcut::from_str(accelerator)
+        .map_err(|e| format!("「{}」的快捷键格式错误：{}", action.label(), e))?;
+      if let Some((other, _)) = parsed.iter().find(|(_, s)| s.id() == shortcut.id()) {
+        return Err(format!(
+          "「{}」和「{}」的快捷键重复",
+          other.label(),
+          action.label()
+        ));
+      }
+      parsed.push((action, shortcut));
+    }
+    Ok(())
+  }
+}
+
+/// 注册失败的快捷键，通常是被其它程序占用了
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutConflict {
+  pub action: ShortcutAction,
+  pub shortcut: String,
+  pub message: String,
+}
+
+/// 最近一次注册快捷键时的冲突，供主窗口查询
+pub type ShortcutConflictsState = Mutex<Vec<ShortcutConflict>>;
+
+/// 重新注册全部快捷键，返回注册失败的快捷键
+pub fn apply_shortcuts<R: Runtime>(
+  app: &AppHandle<R>,
+  settings: &ShortcutSettings,
+) -> Vec<ShortcutConflict> {
+  let global_shortcut = app.global_shortcut();
+  let _ = global_shortcut.unregister_all();
+  let mut conflicts = Vec::new();
+  for (action, accelerator) in settings.bindings() {
+    if accelerator.is_empty() {
+      continue;
+    }
+    let registered = global_shortcut.on_shortcut(accelerator, move |app, _, event| {
+      if event.state != ShortcutState::Pressed {
+        return;
+      }
+      // 和托盘、命令行一样，不会生效的指令直接忽略
+      let bus = app.state::<CounterBusState>();
+      let command = action.command();
+      if command.rejection(&bus.status()).is_none() {
+        let _ = bus.send(command);
+      }
+    });
+    if let Err(e) = registered {
+      conflicts.push(ShortcutConflict {
+        action,
+        shortcut: accelerator.to_string(),
+        message: e.to_string(),
+      });
+    }
+  }
+
+  if let Some(state) = app.try_state::<ShortcutConflictsState>() {
+    *state.lock().unwrap() = conflicts.clone();
+  }
+  conflicts
+}
+
+pub fn setup_shortcuts(app: &mut App, settings: &ShortcutSettings) {
+  app.manage(ShortcutConflictsState::default());
+  for c in apply_shortcuts(app.handle(), settings) {
+    eprintln!(
+      "failed to register shortcut {} for {}: {}",
+      c.shortcut,
+      c.action.label(),
+      c.message
+    );
+  }
+}
+
+/// 最近一次注册快捷键时的冲突
+#[tauri::command]
+pub fn tauri_get_shortcut_conflicts(
+  conflicts: State<'_, ShortcutConflictsState>,
+) -> Vec<ShortcutConflict> {
+  conflicts.lock().unwrap().clone()
+}
@@ -0,0 +1,413 @@
+//! 基于历史记录的统计：每天、每周的工作时长、休息完成情况，以及连续按时休息的天数。
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, NaiveDate};
+use serde::Serialize;
+use tauri::{AppHandle, Runtime, State};
+
+use crate::{
+  history::{BreakEnd, HistoryEntry, HistoryRecord, HistoryState, WorkEnd, now_ms},
+  settings::load_settings,
+  timer::local_day,
+};
+
+const DEFAULT_STATS_DAYS: u32 = 7;
+const DEFAULT_STATS_WEEKS: u32 = 4;
+const MAX_STATS_DAYS: u32 = 366;
+const DAY_MS: u64 = 24 * 3600 * 1000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+  /// 实际工作的时长，不含暂停、离开和休眠
+  pub work_secs: u32,
+  /// 完整休息的次数
+  pub breaks_taken: u32,
+  /// 离开或休眠足够久，自动算作完成的休息次数
+  pub breaks_credited: u32,
+  /// 紧急退出跳过的次数
+  pub breaks_skipped: u32,
+  /// 推迟的次数，推迟后的休息仍会计入完成或跳过
+  pub postpones: u32,
+  /// 完成（含自动算作完成）的休息占全部休息的比例，没有休息时为 None
+  pub compliance: Option<f32>,
+  /// 最长一次连续工作的时长，暂停、离开和休眠都会打断
+  pub longest_work_secs: u32,
+}
+
+impl Stats {
+  fn merge(&mut self, other: &Stats) {
+    self.work_secs += other.work_secs;
+    self.breaks_taken += other.breaks_taken;
+    self.breaks_credited += other.breaks_credited;
+    self.breaks_skipped += other.breaks_skipped;
+    self.postpones += other.postpones;
+    self.longest_work_secs = self.longest_work_secs.max(other.longest_work_secs);
+    self.update_compliance();
+  }
+
+  fn breaks_done(&self) -> u32 {
+    self.breaks_taken + self.breaks_credited
+  }
+
+  fn update_compliance(&mut self) {
+    let total = self.breaks_done() + self.breaks_skipped;
+    self.compliance = (total > 0).then(|| self.breaks_done() as f32 / total as f32);
+  }
+
+  /// 有休息且没有跳过任何一次
+  fn is_compliant(&self) -> bool {
+    self.breaks_done() > 0 && self.breaks_skipped == 0
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyStats {
+  /// YYYY-MM-DD
+  pub date: String,
+  #[serde(flatten)]
+  pub stats: Stats,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyStats {
+  /// 周一的日期，YYYY-MM-DD
+  pub week_start: String,
+  #[serde(flatten)]
+  pub stats: Stats,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Streaks {
+  /// 截止到今天连续按时休息的天数
+  pub current_days: u32,
+  pub longest_days: u32,
+}
+
+fn day_stats(days: &mut BTreeMap<NaiveDate, Stats>, at: u64) -> Option<&mut Stats> {
+  local_day(at).map(|d| days.entry(d).or_default())
+}
+
+/// 按工作或休息开始的日期汇总记录
+fn aggregate(entries: &[HistoryEntry]) -> BTreeMap<NaiveDate, Stats> {
+  let mut days: BTreeMap<NaiveDate, Stats> = BTreeMap::new();
+  for entry in entries {
+    match &entry.record {
+      HistoryRecord::Work {
+        started_at,
+        duration_secs,
+        longest_stretch_secs,
+        ended,
+        ..
+      } => {
+        if let Some(stats) = day_stats(&mut days, *started_at) {
+          stats.work_secs += duration_secs;
+          stats.longest_work_secs = stats.longest_work_secs.max(*longest_stretch_secs);
+        }
+        // 自然休息在离开结束、记录写入时完成
+        if *ended == WorkEnd::Credited
+          && let Some(stats) = day_stats(&mut days, entry.at)
+        {
+          stats.breaks_credited += 1;
+        }
+      }
+      HistoryRecord::Break {
+        started_at, ended, ..
+      } => {
+        if let Some(stats) = day_stats(&mut days, *started_at) {
+          match ended {
+            BreakEnd::Completed => stats.breaks_taken += 1,
+            BreakEnd::Escaped { .. } => stats.breaks_skipped += 1,
+            BreakEnd::Postponed | BreakEnd::OffHours => {}
+          }
+        }
+      }
+      HistoryRecord::Postponed { .. } => {
+        if let Some(stats) = day_stats(&mut days, entry.at) {
+          stats.postpones += 1;
+        }
+      }
+      HistoryRecord::Paused | HistoryRecord::Resumed | HistoryRecord::Deferred { .. } => {}
+    }
+  }
+  days.values_mut().for_each(Stats::update_compliance);
+  days
+}
+
+fn today() -> Result<NaiveDate, String> {
+  local_day(now_ms()).ok_or_else(|| "无法获取当前日期".to_string())
+}
+
+/// 最近 `days` 天（含今天）每天的统计，按日期从早到晚排列
+#[tauri::command]
+pub async fn tauri_get_daily_stats(
+  history: State<'_, HistoryState>,
+  days: Option<u32>,
+) -> Result<Vec<DailyStats>, String> {
+  let days = days.unwrap_or(DEFAULT_STATS_DAYS).clamp(1, MAX_STATS_DAYS);
+  let today = today()?;
+  // 多读一天，避免时区和夏令时导致漏掉最早一天的记录
+  let entries = history.read_since(now_ms().saturating_sub((days as u64 + 1) * DAY_MS));
+  let by_day = aggregate(&entries);
+  Ok(
+    (0..days)
+      .rev()
+      .filter_map(|i| today.checked_sub_days(Days::new(i as u64)))
+      .map(|date| DailyStats {
+        date: date.to_string(),
+        stats: by_day.get(&date).cloned().unwrap_or_default(),
+      })
+      .collect(),
+  )
+}
+
+/// 最近 `weeks` 周（含本周，周一开始）每周的统计，按日期从早到晚排列
+#[tauri::command]
+pub async fn tauri_get_weekly_stats(
+  history: State<'_, HistoryState>,
+  weeks: Option<u32>,
+) -> Result<Vec<WeeklyStats>, String> {
+  let weeks = weeks
+    .unwrap_or(DEFAULT_STATS_WEEKS)
+    .clamp(1, MAX_STATS_DAYS / 7);
+  let today = today()?;
+  let this_week = today - Days::new(today.weekday().num_days_from_monday() as u64);
+  let first_week = this_week - Days::new((weeks as u64 - 1) * 7);
+  let span_days = (today - first_week).num_days() as u64 + 1;
+  let entries = history.read_since(now_ms().saturating_sub((span_days + 1) * DAY_MS));
+  let by_day = aggregate(&entries);
+  Ok(
+    (0..weeks)
+      .map(|i| {
+        let week_start = first_week + Days::new(i as u64 * 7);
+        let mut stats = Stats::default();
+        by_day
+          .range(week_start..week_start + Days::new(7))
+          .for_each(|(_, day)| stats.merge(day));
+        WeeklyStats {
+          week_start: week_start.to_string(),
+          stats,
+        }
+      })
+      .collect(),
+  )
+}
+
+/// 截止到 `today` 连续按时休息的天数。
+///
+/// 有休息（含离开或休眠自动算作完成的休息）且没有跳过的一天算作按时休息；有跳过的一天，以及没有任何休息的工作日都会打断连续天数。
+/// 没有休息的非工作日（例如周末）不打断也不延长，今天还没有休息时也不打断。
+fn streaks(
+  by_day: &BTreeMap<NaiveDate, Stats>,
+  today: NaiveDate,
+  is_workday: impl Fn(NaiveDate) -> bool,
+) -> Streaks {
+  let mut streaks = Streaks::default();
+  let Some(&first) = by_day.keys().next() else {
+    return streaks;
+  };
+  let mut run = 0;
+  for date in first.iter_days().take_while(|&d| d <= today) {
+    match by_day.get(&date) {
+      Some(stats) if stats.breaks_done() + stats.breaks_skipped > 0 => {
+        run = if stats.is_compliant() { run + 1 } else { 0 };
+        streaks.longest_days = streaks.longest_days.max(run);
+      }
+      _ if date != today && is_workday(date) => run = 0,
+      _ => {}
+    }
+  }
+  streaks.current_days = run;
+  streaks
+}
+
+/// 连续按时休息的天数，工作日按设置中的工作时间表判断
+#[tauri::command]
+pub async fn tauri_get_streaks<R: Runtime>(
+  app: AppHandle<R>,
+  history: State<'_, HistoryState>,
+) -> Result<Streaks, String> {
+  let schedule = load_settings(&app).map(|s| s.schedule).unwrap_or_default();
+  let by_day = aggregate(&history.read_since(0));
+  Ok(streaks(&by_day, today()?, |day| schedule.is_workday(day)))
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::{Local, TimeZone};
+
+  use super::*;
+  use crate::{history::HISTORY_SCHEMA_VERSION, schedule::Schedule, settings::EscapeMethod};
+
+  /// 2024 年 1 月 `day` 日 `hour` 点的本地时间，1 月 1 日是周一
+  fn at(day: u32, hour: u32) -> u64 {
+    Local
+      .with_ymd_and_hms(2024, 1, day, hour, 0, 0)
+      .unwrap()
+      .timestamp_millis() as u64
+  }
+
+  fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+  }
+
+  fn entry(at: u64, record: HistoryRecord) -> HistoryEntry {
+    HistoryEntry {
+      v: HISTORY_SCHEMA_VERSION,
+      at,
+      record,
+    }
+  }
+
+  fn work(started_at: u64, duration_secs: u32, longest_stretch_secs: u32) -> HistoryEntry {
+    entry(
+      started_at + duration_secs as u64 * 1000,
+      HistoryRecord::Work {
+        cycle: 1,
+        started_at,
+        duration_secs,
+        longest_stretch_secs,
+        paused_secs: 0,
+        ended: WorkEnd::Break,
+      },
+    )
+  }
+
+  fn rest(started_at: u64, ended: BreakEnd) -> HistoryEntry {
+    entry(
+      started_at + 300 * 1000,
+      HistoryRecord::Break {
+        cycle: 1,
+        long: false,
+        started_at,
+        duration_secs: 300,
+        ended,
+      },
+    )
+  }
+
+  fn escaped() -> BreakEnd {
+    BreakEnd::Escaped {
+      method: EscapeMethod::Repeat,
+      reason: None,
+      skipped_secs: 200,
+    }
+  }
+
+  /// 从 1 月 1 日开始每天的休息情况，None 表示当天没有休息
+  fn days(compliant: &[Option<bool>]) -> BTreeMap<NaiveDate, Stats> {
+    let entries: Vec<_> = (1..)
+      .zip(compliant)
+      .filter_map(|(day, c)| {
+        c.map(|c| rest(at(day, 10), if c { BreakEnd::Completed } else { escaped() }))
+      })
+      .collect();
+    aggregate(&entries)
+  }
+
+  fn workdays(day: NaiveDate) -> bool {
+    Schedule::default().is_workday(day)
+  }
+
+  #[test]
+  fn aggregate_sums_worked_time_and_breaks_per_day() {
+    let entries = [
+      work(at(1, 9), 1500, 1200),
+      rest(at(1, 10), BreakEnd::Completed),
+      entry(at(1, 11), HistoryRecord::Paused),
+      entry(at(1, 12), HistoryRecord::Resumed),
+      work(at(1, 13), 600, 600),
+      entry(
+        at(1, 14),
+        HistoryRecord::Postponed {
+          cycle: 2,
+          from_break: false,
+        },
+      ),
+      rest(at(1, 15), escaped()),
+      rest(at(1, 16), BreakEnd::Postponed),
+      work(at(2, 9), 900, 900),
+    ];
+    let by_day = aggregate(&entries);
+    assert_eq!(
+      by_day[&date(1)],
+      Stats {
+        work_secs: 2100,
+        breaks_taken: 1,
+        breaks_credited: 0,
+        breaks_skipped: 1,
+        postpones: 1,
+        compliance: Some(0.5),
+        longest_work_secs: 1200,
+      }
+    );
+    assert_eq!(by_day[&date(2)].work_secs, 900);
+    assert_eq!(by_day[&date(2)].compliance, None);
+  }
+
+  #[test]
+  fn natural_breaks_count_as_taken() {
+    // 1 月 1 日只有离开时自动算作完成的休息，2 日按时休息
+    let mut credited = work(at(1, 9), 1500, 1500);
+    if let HistoryRecord::Work { ended, .. } = &mut credited.record {
+      *ended = WorkEnd::Credited;
+    }
+    let by_day = aggregate(&[credited, rest(at(2, 10), BreakEnd::Completed)]);
+    assert_eq!(by_day[&date(1)].breaks_credited, 1);
+    assert_eq!(by_day[&date(1)].breaks_taken, 0);
+    assert_eq!(by_day[&date(1)].compliance, Some(1.0));
+
+    let streaks = streaks(&by_day, date(2), workdays);
+    assert_eq!(streaks.current_days, 2);
+  }
+
+  #[test]
+  fn work_counts_on_the_day_it_started() {
+    let by_day = aggregate(&[work(at(1, 23), 7200, 3600)]);
+    assert_eq!(by_day[&date(1)].work_secs, 7200);
+    assert!(!by_day.contains_key(&date(2)));
+  }
+
+  #[test]
+  fn weekends_without_breaks_keep_the_streak() {
+    // 周一到周五按时休息，周末没有休息，下周一按时休息，今天（周二）还没有休息
+    let t = Some(true);
+    let by_day = days(&[t, t, t, t, t, None, None, t]);
+    let streaks = streaks(&by_day, date(9), workdays);
+    assert_eq!(streaks.current_days, 6);
+    assert_eq!(streaks.longest_days, 6);
+  }
+
+  #[test]
+  fn workday_without_breaks_ends_the_streak() {
+    let t = Some(true);
+    let by_day = days(&[t, t, None, t]);
+    let streaks = streaks(&by_day, date(4), workdays);
+    assert_eq!(streaks.current_days, 1);
+    assert_eq!(streaks.longest_days, 2);
+
+    // 之后一直没有休息，连续天数不能停留在最后一次休息的那天
+    let streaks = super::streaks(&by_day, date(10), workdays);
+    assert_eq!(streaks.current_days, 0);
+    assert_eq!(streaks.longest_days, 2);
+  }
+
+  #[test]
+  fn skipped_break_ends_the_streak() {
+    let by_day = days(&[Some(true), Some(false), Some(true)]);
+    let streaks = streaks(&by_day, date(3), workdays);
+    assert_eq!(streaks.current_days, 1);
+    assert_eq!(streaks.longest_days, 1);
+  }
+
+  #[test]
+  fn no_history_has_no_streak() {
+    let streaks = streaks(&BTreeMap::new(), date(1), workdays);
+    assert_eq!(streaks.current_days, 0);
+    assert_eq!(streaks.longest_days, 0);
+  }
+}
@@ -0,0 +1,1359 @@
+//! 工作/休息循环的计时引擎。
+//!
+//! 不依赖 egui 和 tauri，所有时间都从注入的 [`Clock`] 读取，
+//! 界面层只需要定期调用 [`Timer::tick`] 并根据返回的 [`Transition`] 调整窗口。
+//!
+//! 工作时长按不含休眠的单调时钟累计，休眠时长由操作系统提供，
+//! 系统时间被 NTP 校正或手动修改都不会影响计时，也不会被误认为休眠。
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  schedule::Schedule,
+  settings::{Settings, SleepPolicy},
+};
+
+/// 休眠时长由两个时钟相减得到，有几十毫秒的误差，不足这个值的变化不算休眠
+const MIN_SLEEP_MS: u64 = 1000;
+/// 距离正式休息不到这个秒数时跳过护眼小休息，避免两者挤在一起
+const MICRO_BREAK_SKIP_SECS: u32 = 120;
+
+pub trait Clock {
+  /// 不含休眠时间的单调时钟的毫秒数，起点任意
+  fn monotonic_ms(&self) -> u64;
+  /// 累计休眠的毫秒数，起点任意，不受系统时间修改的影响
+  fn suspended_ms(&self) -> u64;
+  /// 系统时间的毫秒数（unix 时间戳），只用于工作时间表、定时暂停和对外展示时刻
+  fn wall_ms(&self) -> u64;
+}
+
+/// 操作系统提供的时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+  fn monotonic_ms(&self) -> u64 {
+    sys::read_clock().0
+  }
+
+  fn suspended_ms(&self) -> u64 {
+    sys::read_clock().1
+  }
+
+  fn wall_ms(&self) -> u64 {
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_millis() as u64)
+      .unwrap_or_default()
+  }
+}
+
+/// 各平台读取单调时钟和休眠时长的方式
+mod sys {
+  /// 返回不含休眠的单调时钟毫秒数，以及开机以来累计休眠的毫秒数。
+  ///
+  /// CLOCK_BOOTTIME 包含休眠时间，CLOCK_MONOTONIC 不包含。
+  #[cfg(target_os = "linux")]
+  pub fn read_clock() -> (u64, u64) {
+    fn clock_ms(clock: libc::clockid_t) -> u64 {
+      let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+      };
+      unsafe { libc::clock_gettime(clock, &mut ts) };
+      ts.tv_sec as u64 * 1000 + ts.tv_nsec as u64 / 1_000_000
+    }
+    let monotonic = clock_ms(libc::CLOCK_MONOTONIC);
+    let boottime = clock_ms(libc::CLOCK_BOOTTIME);
+    (monotonic, boottime.saturating_sub(monotonic))
+  }
+
+  /// mach_continuous_time 包含休眠时间，mach_absolute_time 不包含
+  #[cfg(target_os = "macos")]
+  pub fn read_clock() -> (u64, u64) {
+    #[repr(C)]
+    struct TimebaseInfo {
+      numer: u32,
+      denom: u32,
+    }
+    unsafe extern "C" {
+      fn mach_absolute_time() -> u64;
+      fn mach_continuous_time() -> u64;
+      fn mach_timebase_info(info: *mut TimebaseInfo) -> i32;
+    }
+    let mut info = TimebaseInfo { numer: 1, denom: 1 };
+    let (absolute, continuous) = unsafe {
+      mach_timebase_info(&mut info);
+      (mach_absolute_time(), mach_continuous_time())
+    };
+    let to_ms = |ticks: u64| {
+      (ticks as u128 * info.numer as u128 / info.denom.max(1) as u128 / 1_000_000) as u64
+    };
+    (to_ms(absolute), to_ms(continuous.saturating_sub(absolute)))
+  }
+
+  /// QueryPerformanceCounter 包含休眠时间，QueryUnbiasedInterruptTime 不包含
+  #[cfg(windows)]
+  pub fn read_clock() -> (u64, u64) {
+    use winapi::um::{
+      profileapi::{QueryPerformanceCounter, QueryPerformanceFrequency},
+      realtimeapiset::QueryUnbiasedInterruptTime,
+      winnt::LARGE_INTEGER,
+    };
+
+    let mut unbiased = 0u64;
+    let (counter, frequency) = unsafe {
+      let mut counter: LARGE_INTEGER = std::mem::zeroed();
+      let mut frequency: LARGE_INTEGER = std::mem::zeroed();
+      QueryUnbiasedInterruptTime(&mut unbiased);
+      QueryPerformanceCounter(&mut counter);
+      QueryPerformanceFrequency(&mut frequency);
+      (*counter.QuadPart() as u128, *frequency.QuadPart() as u128)
+    };
+    // 中断时间的单位是 100 纳秒
+    let monotonic = unbiased / 10_000;
+    let total = (counter * 1000 / frequency.max(1)) as u64;
+    (monotonic, total.saturating_sub(monotonic))
+  }
+
+  /// 其它平台无法获取休眠时长，休眠期间计时器只是停止
+  #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+  pub fn read_clock() -> (u64, u64) {
+    static ORIGIN: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    let origin = ORIGIN.get_or_init(std::time::Instant::now);
+    (origin.elapsed().as_millis() as u64, 0)
+  }
+}
+
+/// 测试用的时钟，时间只在测试代码调用时推进。克隆出的时钟共享同一个时间。
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub struct FakeClock {
+  mono_ms: std::rc::Rc<std::cell::Cell<u64>>,
+  suspended_ms: std::rc::Rc<std::cell::Cell<u64>>,
+  wall_ms: std::rc::Rc<std::cell::Cell<u64>>,
+}
+
+#[cfg(test)]
+impl FakeClock {
+  pub fn new(wall_ms: u64) -> Self {
+    let clock = Self::default();
+    clock.wall_ms.set(wall_ms);
+    clock
+  }
+
+  /// 正常经过 `ms` 毫秒
+  pub fn advance(&self, ms: u64) {
+    self.mono_ms.set(self.mono_ms.get() + ms);
+    self.wall_ms.set(self.wall_ms.get() + ms);
+  }
+
+  /// 系统休眠 `ms` 毫秒，单调时钟停止
+  pub fn sleep(&self, ms: u64) {
+    self.suspended_ms.set(self.suspended_ms.get() + ms);
+    self.wall_ms.set(self.wall_ms.get() + ms);
+  }
+
+  /// 修改系统时间，例如 NTP 校正或手动调整
+  pub fn set_wall(&self, wall_ms: u64) {
+    self.wall_ms.set(wall_ms);
+  }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+  fn monotonic_ms(&self) -> u64 {
+    self.mono_ms.get()
+  }
+
+  fn suspended_ms(&self) -> u64 {
+    self.suspended_ms.get()
+  }
+
+  fn wall_ms(&self) -> u64 {
+    self.wall_ms.get()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Phase {
+  /// 工作计时中
+  Counting,
+  /// 休息中，屏幕被锁定
+  Blocking,
+  /// 休息结束，等待用户移动鼠标解锁
+  BlockEnd,
+}
+
+/// 推迟休息开始的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeferReason {
+  /// 主屏幕上有全屏窗口，例如演示或视频会议
+  Fullscreen,
+  /// 设置中列出的程序正在运行
+  Process,
+}
+
+/// 一段已经结束的工作里实际工作的时长，不含暂停、离开和休眠
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkSpan {
+  pub worked_secs: u32,
+  /// 最长一次连续工作的秒数
+  pub longest_stretch_secs: u32,
+}
+
+/// 阶段切换，界面层据此调整窗口
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+  EnterBlocking,
+  BlockingEnd,
+  EnterCounting,
+  /// 工作期间离开足够久，直接算作完成了一次休息，重新开始工作计时
+  BreakCredited,
+  /// 即将开始休息，进入预警阶段
+  BreakWarning,
+  /// 护眼小休息开始，只提醒不锁屏
+  MicroBreakStart,
+  MicroBreakEnd,
+  /// 进入工作时间，从新的一轮开始计时
+  WorkHoursStart,
+  /// 离开工作时间，计时器休眠
+  WorkHoursEnd,
+  /// 工作时长已到，但因为 [`DeferReason`] 暂缓进入休息
+  BreakDeferred,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerConfig {
+  pub work_secs: u32,
+  pub rest_secs: u32,
+  /// 长休息时长
+  pub long_rest_secs: u32,
+  /// 每隔多少轮工作进行一次长休息，0 表示不启用
+  pub long_rest_every: u32,
+  /// 护眼小休息的间隔，0 表示不启用
+  pub micro_break_every_secs: u32,
+  /// 护眼小休息的时长
+  pub micro_break_secs: u32,
+  /// 休息开始前多少秒进入预警阶段，0 表示不预警
+  pub warning_secs: u32,
+  /// 每次推迟休息的秒数
+  pub postpone_secs: u32,
+  /// 每轮工作最多推迟几次
+  pub postpone_per_cycle: u32,
+  /// 每天最多推迟几次
+  pub postpone_per_day: u32,
+  /// 严格模式下休息期间不能紧急退出和推迟
+  pub strict_mode: bool,
+  /// 休息期间不允许关闭严格模式
+  pub strict_lock_during_break: bool,
+  pub sleep_policy: SleepPolicy,
+  pub schedule: Schedule,
+  /// 主屏幕有全屏窗口时暂缓休息
+  pub fullscreen_dnd: bool,
+  /// 暂缓休息的最长秒数，超过后无论如何都开始休息
+  pub defer_max_secs: u32,
+  /// 用户空闲超过这个秒数视为离开，0 表示不检测
+  pub idle_threshold_secs: u32,
+  /// 暂停的最长秒数，无限期暂停也会在这之后自动继续，0 表示不限制
+  pub max_pause_secs: u32,
+}
+
+impl From<&Settings> for TimerConfig {
+  fn from(settings: &Settings) -> Self {
+    Self {
+      work_secs: settings.work_secs,
+      rest_secs: settings.rest_secs,
+      long_rest_secs: settings.long_rest_secs,
+      long_rest_every: settings.long_rest_every,
+      micro_break_every_secs: settings.micro_break_every_secs,
+      micro_break_secs: settings.micro_break_secs,
+      warning_secs: settings.warning_secs,
+      postpone_secs: settings.postpone_secs,
+      postpone_per_cycle: settings.postpone_per_cycle,
+      postpone_per_day: settings.postpone_per_day,
+      strict_mode: settings.strict_mode,
+      strict_lock_during_break: settings.strict_lock_during_break,
+      sleep_policy: settings.sleep_policy,
+      schedule: settings.schedule,
+      fullscreen_dnd: settings.fullscreen_dnd,
+      defer_max_secs: settings.defer_max_secs,
+      idle_threshold_secs: settings.idle_threshold_secs,
+      max_pause_secs: settings.max_pause_secs,
+    }
+  }
+}
+
+/// 测试用的配置：工作 10 分钟、休息 2 分钟，不预警，不启用长休息和护眼小休息
+#[cfg(test)]
+impl Default for TimerConfig {
+  fn default() -> Self {
+    Self {
+      work_secs: 600,
+      rest_secs: 120,
+      long_rest_secs: 900,
+      long_rest_every: 0,
+      micro_break_every_secs: 0,
+      micro_break_secs: 20,
+      warning_secs: 0,
+      postpone_secs: 300,
+      postpone_per_cycle: 1,
+      postpone_per_day: 3,
+      strict_mode: false,
+      strict_lock_during_break: false,
+      sleep_policy: SleepPolicy::Rest,
+      schedule: Schedule::default(),
+      fullscreen_dnd: false,
+      defer_max_secs: 300,
+      idle_threshold_secs: 60,
+      max_pause_secs: 0,
+    }
+  }
+}
+
+pub struct Timer<C: Clock> {
+  clock: C,
+  config: TimerConfig,
+  /// 休息期间修改的配置，等回到工作阶段再生效
+  pending_config: Option<TimerConfig>,
+  phase: Phase,
+  /// 当前是第几轮工作，从 1 开始，长休息结束后重新从 1 计数
+  cycle: u32,
+  /// 当前阶段已累计的毫秒数，暂停期间不累计
+  elapsed_ms: u64,
+  /// 距离上次护眼小休息累计的工作毫秒数
+  micro_elapsed_ms: u64,
+  /// 本段工作实际工作的毫秒数，不受推迟和配置缩放的影响
+  worked_ms: u64,
+  /// 当前这次连续工作的毫秒数，暂停、离开和休眠都会打断
+  stretch_ms: u64,
+  /// 本段工作中已经结束的最长一次连续工作的毫秒数
+  longest_stretch_ms: u64,
+  /// 上一段结束的工作
+  last_work: WorkSpan,
+  /// 护眼小休息进行中时，已经过的毫秒数
+  micro_active_ms: Option<u64>,
+  /// 本轮工作是否已经发出过休息预警
+  warned: bool,
+  last_mono_ms: u64,
+  last_suspended_ms: u64,
+  last_wall_ms: u64,
+  /// 上次 tick 以来识别到的休眠时长
+  slept_ms: u64,
+  paused: bool,
+  /// 用户离开中，工作时间不累计
+  idle: bool,
+  /// 本次离开已经算作完成过一次休息，避免重复计算
+  idle_credited: bool,
+  /// 定时暂停的自动恢复时刻（系统时间）
+  pause_until: Option<u64>,
+  /// 本轮已推迟的次数
+  postponed_in_cycle: u32,
+  /// `postpone_day` 这一天已推迟的次数
+  postponed_today: u32,
+  postpone_day: Option<NaiveDate>,
+  /// 处于工作时间之外，计时器休眠
+  off_hours: bool,
+  /// 界面层上报的暂缓休息原因
+  busy: Option<DeferReason>,
+  /// 工作时长已到，正在暂缓休息
+  deferring: bool,
+}
+
+impl<C: Clock> Timer<C> {
+  pub fn new(config: TimerConfig, clock: C) -> Self {
+    let last_mono_ms = clock.monotonic_ms();
+    let last_suspended_ms = clock.suspended_ms();
+    let last_wall_ms = clock.wall_ms();
+    Self {
+      clock,
+      config,
+      pending_config: None,
+      phase: Phase::Counting,
+      cycle: 1,
+      elapsed_ms: 0,
+      micro_elapsed_ms: 0,
+      worked_ms: 0,
+      stretch_ms: 0,
+      longest_stretch_ms: 0,
+      last_work: WorkSpan::default(),
+      micro_active_ms: None,
+      warned: false,
+      last_mono_ms,
+      last_suspended_ms,
+      last_wall_ms,
+      slept_ms: 0,
+      paused: false,
+      idle: false,
+      idle_credited: false,
+      pause_until: None,
+      postponed_in_cycle: 0,
+      postponed_today: 0,
+      postpone_day: local_day(last_wall_ms),
+      off_hours: !config.schedule.is_working_at(last_wall_ms),
+      busy: None,
+      deferring: false,
+    }
+  }
+
+  /// 设置今天已经推迟过的次数，用于重启后恢复每天的推迟限制
+  pub fn with_postponed_today(mut self, count: u32) -> Self {
+    self.postponed_today = count;
+    self
+  }
+
+  /// 把上次 tick 到现在的时间累计进当前阶段。
+  ///
+  /// 休眠期间不算工作时间，但算休息时间。
+  /// 定时暂停到期时自动恢复，到期之后的时间照常累计。
+  fn advance(&mut self) {
+    let mono = self.clock.monotonic_ms();
+    let wall = self.clock.wall_ms();
+    let suspended = self.clock.suspended_ms();
+    let awake = mono.saturating_sub(self.last_mono_ms);
+    let mut asleep = suspended.saturating_sub(self.last_suspended_ms);
+    self.last_mono_ms = mono;
+    self.last_wall_ms = wall;
+    if asleep >= MIN_SLEEP_MS {
+      self.last_suspended_ms = suspended;
+    } else {
+      asleep = 0;
+    }
+    self.slept_ms += asleep;
+
+    if !matches!(self.phase, Phase::Counting) {
+      self.elapsed_ms += awake + asleep;
+      return;
+    }
+    if let Some(active) = self.micro_active_ms.as_mut() {
+      *active += awake;
+    }
+    if asleep > 0 {
+      self.end_stretch();
+    }
+    let worked = if self.idle || self.off_hours {
+      0
+    } else if !self.paused {
+      awake
+    } else if let Some(until) = self.pause_until
+      && wall >= until
+    {
+      self.paused = false;
+      self.pause_until = None;
+      awake.min(wall - until)
+    } else {
+      0
+    };
+    if worked == 0 && awake > 0 {
+      self.end_stretch();
+    }
+    self.elapsed_ms += worked;
+    self.micro_elapsed_ms += worked;
+    self.worked_ms += worked;
+    self.stretch_ms += worked;
+  }
+
+  fn end_stretch(&mut self) {
+    self.longest_stretch_ms = self.longest_stretch_ms.max(self.stretch_ms);
+    self.stretch_ms = 0;
+  }
+
+  /// 切换阶段。离开工作阶段（包括重新开始本轮工作）时记下这段工作实际工作的时长。
+  fn enter(&mut self, phase: Phase) {
+    if matches!(self.phase, Phase::Counting) {
+      self.end_stretch();
+      self.last_work = WorkSpan {
+        worked_secs: (self.worked_ms / 1000) as u32,
+        longest_stretch_secs: (self.longest_stretch_ms / 1000) as u32,
+      };
+    }
+    self.worked_ms = 0;
+    self.longest_stretch_ms = 0;
+    if matches!(phase, Phase::Counting)
+      && let Some(config) = self.pending_config.take()
+    {
+      self.config = config;
+    }
+    self.phase = phase;
+    self.elapsed_ms = 0;
+    self.micro_elapsed_ms = 0;
+    self.micro_active_ms = None;
+    self.warned = false;
+    self.deferring = false;
+    self.slept_ms = 0;
+    self.last_mono_ms = self.clock.monotonic_ms();
+    self.last_suspended_ms = self.clock.suspended_ms();
+    self.last_wall_ms = self.clock.wall_ms();
+  }
+
+  /// 本轮休息结束，进入下一轮工作
+  fn finish_cycle(&mut self) {
+    self.cycle = if self.is_long_break() {
+      1
+    } else {
+      self.cycle + 1
+    };
+    self.postponed_in_cycle = 0;
+    self.enter(Phase::Counting);
+  }
+
+  /// 离开了 `away_ms` 毫秒，达到本轮的休息时长时算作完成一次休息。
+  /// 离开时长达到长休息时长时重新从第一轮开始；本轮该长休息时，较短的离开不算数。
+  fn credit_break(&mut self, away_ms: u64) -> Option<Transition> {
+    if self.config.long_rest_every > 0 && away_ms >= secs_to_ms(self.config.long_rest_secs) {
+      self.cycle = 1;
+      self.postponed_in_cycle = 0;
+      self.enter(Phase::Counting);
+    } else if away_ms >= secs_to_ms(self.rest_secs()) {
+      self.finish_cycle();
+    } else {
+      return None;
+    }
+    // 唤醒后空闲时长里还包含这段离开的时间，不要再算一次
+    self.idle_credited = true;
+    Some(Transition::BreakCredited)
+  }
+
+  /// 推进时间，到点时切换阶段。需要被定期调用。
+  pub fn tick(&mut self) -> Option<Transition> {
+    self.advance();
+    let working = self.config.schedule.is_working_at(self.last_wall_ms);
+    if working == self.off_hours {
+      // 进入或离开工作时间，都从新的一轮开始
+      self.off_hours = !working;
+      self.cycle = 1;
+      self.postponed_in_cycle = 0;
+      self.enter(Phase::Counting);
+      return Some(if working {
+        Transition::WorkHoursStart
+      } else {
+        Transition::WorkHoursEnd
+      });
+    }
+    if self.off_hours {
+      self.slept_ms = 0;
+      return None;
+    }
+    let slept_ms = std::mem::take(&mut self.slept_ms);
+    if matches!(self.phase, Phase::Counting)
+      && matches!(self.config.sleep_policy, SleepPolicy::Rest)
+      && let Some(t) = self.credit_break(slept_ms)
+    {
+      // 休眠时间足够一次休息，进入下一轮工作；较短的休眠只是不计入工作时间，相当于暂停
+      return Some(t);
+    }
+    match self.phase {
+      Phase::Counting if self.elapsed_ms >= secs_to_ms(self.config.work_secs) => {
+        let over_ms = self.elapsed_ms - secs_to_ms(self.config.work_secs);
+        if self.busy.is_some() && over_ms < secs_to_ms(self.config.defer_max_secs) {
+          if self.deferring {
+            return None;
+          }
+          self.deferring = true;
+          return Some(Transition::BreakDeferred);
+        }
+        self.enter(Phase::Blocking);
+        Some(Transition::EnterBlocking)
+      }
+      Phase::Blocking if self.elapsed_ms >= secs_to_ms(self.rest_secs()) => {
+        self.enter(Phase::BlockEnd);
+        Some(Transition::BlockingEnd)
+      }
+      Phase::Counting if !self.warned && self.is_warning() => {
+        self.warned = true;
+        Some(Transition::BreakWarning)
+      }
+      Phase::Counting => self.tick_micro_break(),
+      _ => None,
+    }
+  }
+
+  /// 护眼小休息独立于正式休息计时，只在工作阶段进行，正式休息开始时被清零
+  fn tick_micro_break(&mut self) -> Option<Transition> {
+    if let Some(active) = self.micro_active_ms {
+      if active < secs_to_ms(self.config.micro_break_secs) {
+        return None;
+      }
+      self.micro_active_ms = None;
+      return Some(Transition::MicroBreakEnd);
+    }
+    let every = self.config.micro_break_every_secs;
+    if every == 0 || self.micro_elapsed_ms < secs_to_ms(every) {
+      return None;
+    }
+    self.micro_elapsed_ms = 0;
+    if self.remaining_secs() <= MICRO_BREAK_SKIP_SECS {
+      return None;
+    }
+    self.micro_active_ms = Some(0);
+    Some(Transition::MicroBreakStart)
+  }
+
+  /// 应用新的配置。
+  ///
+  /// 工作阶段按比例缩放已累计的时间，保持本轮的进度百分比不变，
+  /// 避免缩短工作时长后立即锁屏；休息期间不打断当前休息，新配置在回到工作阶段时生效。
+  pub fn apply_config(&mut self, config: TimerConfig) {
+    self.advance();
+    if !matches!(self.phase, Phase::Counting) {
+      // 严格模式的开关立即生效，但设置了休息中锁定时，要等休息结束才能关闭
+      if config.strict_mode || !self.config.strict_lock_during_break {
+        self.config.strict_mode = config.strict_mode;
+      }
+      self.pending_config = Some(config);
+      return;
+    }
+    let old_work_ms = secs_to_ms(self.config.work_secs);
+    if old_work_ms > 0 {
+      self.elapsed_ms = (self.elapsed_ms as u128 * secs_to_ms(config.work_secs) as u128
+        / old_work_ms as u128) as u64;
+    }
+    self.config = config;
+    self.pending_config = None;
+  }
+
+  /// 上报用户当前已空闲的秒数，需要被定期调用。
+  ///
+  /// 空闲达到阈值时视为离开：已经计入的这段空闲时间会被扣除，离开期间不再累计工作时间；
+  /// 空闲达到本轮的休息时长时算作完成一次休息，本轮工作重新开始。只在 Counting 阶段生效。
+  pub fn report_idle(&mut self, idle_secs: u32) -> Option<Transition> {
+    let threshold = self.config.idle_threshold_secs;
+    if threshold == 0 || idle_secs < threshold {
+      self.idle = false;
+      self.idle_credited = false;
+      return None;
+    }
+    if !matches!(self.phase, Phase::Counting) || self.off_hours {
+      return None;
+    }
+    if !self.idle {
+      self.advance();
+      self.idle = true;
+      if !self.paused {
+        let idle_ms = secs_to_ms(idle_secs);
+        self.elapsed_ms = self.elapsed_ms.saturating_sub(idle_ms);
+        self.micro_elapsed_ms = self.micro_elapsed_ms.saturating_sub(idle_ms);
+        self.worked_ms = self.worked_ms.saturating_sub(idle_ms);
+        self.stretch_ms = self.stretch_ms.saturating_sub(idle_ms);
+      }
+      self.end_stretch();
+    }
+    if self.idle_credited {
+      return None;
+    }
+    self.credit_break(secs_to_ms(idle_secs))
+  }
+
+  /// 用户是否处于离开状态
+  pub fn is_idle(&self) -> bool {
+    self.idle
+  }
+
+  /// 暂停工作计时，只在工作时间内的 Counting 阶段生效，返回是否生效。
+  /// `secs` 不为空时到点自动继续，时长受 `max_pause_secs` 限制。
+  pub fn pause(&mut self, secs: Option<u32>) -> bool {
+    self.advance();
+    if !matches!(self.phase, Phase::Counting) || self.off_hours {
+      return false;
+    }
+    let secs = match (secs, self.config.max_pause_secs) {
+      (secs, 0) => secs,
+      (Some(secs), max) => Some(secs.min(max)),
+      (None, max) => Some(max),
+    };
+    self.paused = true;
+    self.pause_until = secs.map(|s| self.last_wall_ms + secs_to_ms(s));
+    true
+  }
+
+  /// 暂停到下一个工作日开始工作时，没有工作日时无限期暂停。返回是否生效。
+  pub fn pause_until_next_workday(&mut self) -> bool {
+    self.advance();
+    self.pause(self.next_workday_in_secs())
+  }
+
+  /// 距离下一个工作日开始工作的秒数
+  pub fn next_workday_in_secs(&self) -> Option<u32> {
+    self
+      .config
+      .schedule
+      .next_workday_start(self.last_wall_ms)
+      .map(|at| (at.saturating_sub(self.last_wall_ms) / 1000) as u32)
+  }
+
+  /// 暂停的最长秒数，None 表示不限制
+  pub fn pause_limit_secs(&self) -> Option<u32> {
+    let max = self.config.max_pause_secs;
+    (max > 0).then_some(max)
+  }
+
+  pub fn resume(&mut self) {
+    self.advance();
+    self.paused = false;
+    self.pause_until = None;
+  }
+
+  /// 重新开始本轮工作计时，休息期间忽略。返回是否生效。
+  pub fn reset(&mut self) -> bool {
+    if !matches!(self.phase, Phase::Counting) {
+      return false;
+    }
+    self.enter(Phase::Counting);
+    self.paused = false;
+    self.pause_until = None;
+    true
+  }
+
+  /// 休息结束后用户确认解锁
+  pub fn unlock(&mut self) -> Option<Transition> {
+    if !matches!(self.phase, Phase::BlockEnd) {
+      return None;
+    }
+    self.finish_cycle();
+    Some(Transition::EnterCounting)
+  }
+
+  /// 紧急退出休息，本轮算作完成。退出方式由界面决定，严格模式下无效。
+  pub fn escape(&mut self) -> Option<Transition> {
+    if !matches!(self.phase, Phase::Blocking) || self.config.strict_mode {
+      return None;
+    }
+    self.finish_cycle();
+    Some(Transition::EnterCounting)
+  }
+
+  /// 推迟休息，休息会在 `postpone_secs` 秒后开始。
+  ///
+  /// 工作阶段只在距离休息不足推迟时长时可用（通常是预警阶段）；
+  /// 休息中推迟会结束本次休息回到工作阶段，本轮不算完成。受每轮和每天的次数限制。
+  pub fn postpone(&mut self) -> Result<Option<Transition>, &'static str> {
+    if self.is_strict_break() {
+      return Err("严格模式下休息期间不能推迟");
+    }
+    if self.postpones_left() == 0 {
+      return Err("推迟次数已用完");
+    }
+    let transition = match self.phase {
+      Phase::Counting => {
+        self.advance();
+        if self.too_early_to_postpone() {
+          return Err("距离休息还早，无需推迟");
+        }
+        None
+      }
+      Phase::Blocking => {
+        self.enter(Phase::Counting);
+        Some(Transition::EnterCounting)
+      }
+      Phase::BlockEnd => return Err("休息已经结束"),
+    };
+    // 回到工作阶段时可能换上了休息期间修改的配置
+    let work_ms = secs_to_ms(self.config.work_secs);
+    let postpone_ms = secs_to_ms(self.config.postpone_secs);
+    self.elapsed_ms = work_ms.saturating_sub(postpone_ms);
+    self.warned = false;
+    self.deferring = false;
+
+    let today = local_day(self.last_wall_ms);
+    if self.postpone_day != today {
+      self.postpone_day = today;
+      self.postponed_today = 0;
+    }
+    self.postponed_in_cycle += 1;
+    self.postponed_today += 1;
+    Ok(transition)
+  }
+
+  fn too_early_to_postpone(&self) -> bool {
+    secs_to_ms(self.config.work_secs).saturating_sub(self.elapsed_ms)
+      >= secs_to_ms(self.config.postpone_secs)
+  }
+
+  /// 现在是否可以推迟，判断条件和 [`Timer::postpone`] 一致
+  pub fn can_postpone(&self) -> bool {
+    self.postpones_left() > 0
+      && match self.phase {
+        Phase::Counting => !self.too_early_to_postpone(),
+        Phase::Blocking => true,
+        Phase::BlockEnd => false,
+      }
+  }
+
+  /// 跳过剩余的工作时间立即开始休息，只在工作时间内的工作阶段可用
+  pub fn break_now(&mut self) -> Option<Transition> {
+    if !matches!(self.phase, Phase::Counting) || self.off_hours {
+      return None;
+    }
+    self.advance();
+    self.paused = false;
+    self.pause_until = None;
+    self.enter(Phase::Blocking);
+    Some(Transition::EnterBlocking)
+  }
+
+  /// 本轮还能推迟的次数，同时受每天的次数限制。严格模式下休息期间不能推迟。
+  pub fn postpones_left(&self) -> u32 {
+    if self.is_strict_break() {
+      return 0;
+    }
+    let today = if self.postpone_day == local_day(self.last_wall_ms) {
+      self.postponed_today
+    } else {
+      0
+    };
+    let per_cycle = self
+      .config
+      .postpone_per_cycle
+      .saturating_sub(self.postponed_in_cycle);
+    per_cycle.min(self.config.postpone_per_day.saturating_sub(today))
+  }
+
+  /// 上报当前是否需要暂缓休息，需要被定期调用。暂缓的时长受 `defer_max_secs` 限制。
+  pub fn report_busy(&mut self, reason: Option<DeferReason>) {
+    self.busy = reason;
+  }
+
+  /// 工作时长已到、正在暂缓休息的原因
+  pub fn deferred_by(&self) -> Option<DeferReason> {
+    if self.deferring { self.busy } else { None }
+  }
+
+  /// 是否处于工作时间之外
+  pub fn is_off_hours(&self) -> bool {
+    self.off_hours
+  }
+
+  /// 是否处于严格模式下的休息中，此时不能跳过休息
+  pub fn is_strict_break(&self) -> bool {
+    self.config.strict_mode && !matches!(self.phase, Phase::Counting)
+  }
+
+  pub fn phase(&self) -> Phase {
+    self.phase
+  }
+
+  /// 护眼小休息还剩的秒数，不在小休息中时返回 None
+  pub fn micro_break_remaining_secs(&self) -> Option<u32> {
+    let active = self.micro_active_ms?;
+    let left = secs_to_ms(self.config.micro_break_secs).saturating_sub(active);
+    Some(left.div_ceil(1000) as u32)
+  }
+
+  /// 是否处于休息前的预警阶段，暂缓休息期间不算
+  pub fn is_warning(&self) -> bool {
+    let warning = self.config.warning_secs;
+    warning > 0
+      && matches!(self.phase, Phase::Counting)
+      && !self.paused
+      && !self.deferring
+      && self.remaining_secs() <= warning
+  }
+
+  pub fn cycle(&self) -> u32 {
+    self.cycle
+  }
+
+  /// 本轮工作之后（或正在进行）的休息是否为长休息
+  pub fn is_long_break(&self) -> bool {
+    let every = self.config.long_rest_every;
+    every > 0 && self.cycle.is_multiple_of(every)
+  }
+
+  /// 本轮的休息时长
+  pub fn rest_secs(&self) -> u32 {
+    if self.is_long_break() {
+      self.config.long_rest_secs
+    } else {
+      self.config.rest_secs
+    }
+  }
+
+  pub fn is_paused(&self) -> bool {
+    self.paused
+  }
+
+  /// 上一段结束的工作实际工作了多久，在离开工作阶段的 [`Transition`] 之后读取
+  pub fn last_work(&self) -> WorkSpan {
+    self.last_work
+  }
+
+  /// 定时暂停还剩的秒数，无限期暂停或未暂停时返回 None
+  pub fn pause_remaining_secs(&self) -> Option<u32> {
+    let until = self.pause_until?;
+    Some((until.saturating_sub(self.last_wall_ms) / 1000) as u32)
+  }
+
+  pub fn config(&self) -> &TimerConfig {
+    &self.config
+  }
+
+  /// 当前阶段已经过的秒数
+  pub fn elapsed_secs(&self) -> u32 {
+    (self.elapsed_ms / 1000) as u32
+  }
+
+  /// 当前阶段剩余的秒数，BlockEnd 阶段恒为 0
+  pub fn remaining_secs(&self) -> u32 {
+    let total = match self.phase {
+      Phase::Counting => self.config.work_secs,
+      Phase::Blocking => self.rest_secs(),
+      Phase::BlockEnd => 0,
+    };
+    total.saturating_sub(self.elapsed_secs())
+  }
+
+  /// 下一次休息开始的时刻（unix 毫秒时间戳）。暂停或不在工作阶段时返回 None。
+  pub fn next_break_at(&self) -> Option<u64> {
+    if self.paused || !matches!(self.phase, Phase::Counting) {
+      return None;
+    }
+    let left = secs_to_ms(self.config.work_secs).saturating_sub(self.elapsed_ms);
+    Some(self.last_wall_ms + left)
+  }
+}
+
+/// 系统时间所在的本地日期
+pub fn local_day(wall_ms: u64) -> Option<NaiveDate> {
+  Local
+    .timestamp_millis_opt(wall_ms as i64)
+    .single()
+    .map(|d| d.date_naive())
+}
+
+fn secs_to_ms(secs: u32) -> u64 {
+  secs as u64 * 1000
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// 2024-01-01 12:00:00 UTC
+  const START_MS: u64 = 1_704_110_400_000;
+
+  fn timer(config: TimerConfig) -> (Timer<FakeClock>, FakeClock) {
+    let clock = FakeClock::new(START_MS);
+    (Timer::new(config, clock.clone()), clock)
+  }
+
+  /// 每秒 tick 一次，返回 `secs` 秒内的第一个阶段切换
+  fn run(timer: &mut Timer<FakeClock>, clock: &FakeClock, secs: u32) -> Option<Transition> {
+    for _ in 0..secs {
+      clock.advance(1000);
+      if let Some(t) = timer.tick() {
+        return Some(t);
+      }
+    }
+    None
+  }
+
+  #[test]
+  fn work_break_cycle() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    assert_eq!(run(&mut timer, &clock, 599), None);
+    assert_eq!(timer.remaining_secs(), 1);
+    assert_eq!(run(&mut timer, &clock, 1), Some(Transition::EnterBlocking));
+    assert_eq!(timer.phase(), Phase::Blocking);
+    assert_eq!(timer.remaining_secs(), 120);
+
+    assert_eq!(timer.unlock(), None);
+    assert_eq!(run(&mut timer, &clock, 120), Some(Transition::BlockingEnd));
+    assert_eq!(timer.phase(), Phase::BlockEnd);
+    assert_eq!(timer.remaining_secs(), 0);
+    // 等待解锁期间不会自动回到工作阶段
+    assert_eq!(run(&mut timer, &clock, 600), None);
+
+    assert_eq!(timer.unlock(), Some(Transition::EnterCounting));
+    assert_eq!(timer.phase(), Phase::Counting);
+    assert_eq!(timer.cycle(), 2);
+    assert_eq!(timer.elapsed_secs(), 0);
+  }
+
+  #[test]
+  fn pause_and_resume() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 100);
+    timer.pause(None);
+    assert!(timer.is_paused());
+    assert_eq!(timer.pause_remaining_secs(), None);
+    assert_eq!(run(&mut timer, &clock, 3600), None);
+    assert_eq!(timer.elapsed_secs(), 100);
+    assert_eq!(timer.next_break_at(), None);
+
+    timer.resume();
+    assert!(!timer.is_paused());
+    run(&mut timer, &clock, 10);
+    assert_eq!(timer.elapsed_secs(), 110);
+  }
+
+  #[test]
+  fn timed_pause_resumes_itself() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 100);
+    timer.pause(Some(60));
+    assert_eq!(timer.pause_remaining_secs(), Some(60));
+    run(&mut timer, &clock, 90);
+    assert!(!timer.is_paused());
+    // 到期之后的 30 秒照常累计
+    assert_eq!(timer.elapsed_secs(), 130);
+  }
+
+  #[test]
+  fn max_pause_caps_pauses() {
+    let config = TimerConfig {
+      max_pause_secs: 600,
+      ..TimerConfig::default()
+    };
+    let (mut timer, _) = timer(config);
+    timer.pause(None);
+    assert_eq!(timer.pause_remaining_secs(), Some(600));
+    timer.pause(Some(3600));
+    assert_eq!(timer.pause_remaining_secs(), Some(600));
+    timer.pause(Some(60));
+    assert_eq!(timer.pause_remaining_secs(), Some(60));
+  }
+
+  #[test]
+  fn pause_until_next_workday_includes_today() {
+    let monday = |hour| {
+      Local
+        .with_ymd_and_hms(2024, 1, 1, hour, 0, 0)
+        .unwrap()
+        .timestamp_millis() as u64
+    };
+    let config = TimerConfig {
+      schedule: Schedule {
+        enabled: true,
+        ..Schedule::default()
+      },
+      ..TimerConfig::default()
+    };
+    let clock = FakeClock::new(monday(7));
+    let timer = Timer::new(config, clock.clone());
+    assert_eq!(timer.next_workday_in_secs(), Some(2 * 3600));
+
+    // 今天的工作已经开始，暂停到明天
+    clock.set_wall(monday(10));
+    let mut timer = Timer::new(config, clock.clone());
+    assert!(timer.pause_until_next_workday());
+    assert_eq!(timer.pause_remaining_secs(), Some(23 * 3600));
+
+    // 周五晚上到下周一
+    clock.set_wall(monday(20) + 4 * 24 * 3600 * 1000);
+    let timer = Timer::new(config, clock.clone());
+    assert_eq!(
+      timer.next_workday_in_secs(),
+      Some(2 * 24 * 3600 + 13 * 3600)
+    );
+  }
+
+  #[test]
+  fn pause_during_blocking_has_no_effect() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 600);
+    assert_eq!(timer.phase(), Phase::Blocking);
+    assert!(!timer.pause(None));
+    assert!(!timer.is_paused());
+
+    assert_eq!(run(&mut timer, &clock, 120), Some(Transition::BlockingEnd));
+    assert!(!timer.pause(Some(60)));
+    timer.unlock();
+    // 下一轮照常计时
+    assert!(!timer.is_paused());
+    run(&mut timer, &clock, 10);
+    assert_eq!(timer.elapsed_secs(), 10);
+  }
+
+  #[test]
+  fn pause_has_no_effect_off_hours() {
+    let monday_7am = Local
+      .with_ymd_and_hms(2024, 1, 1, 7, 0, 0)
+      .unwrap()
+      .timestamp_millis() as u64;
+    let config = TimerConfig {
+      schedule: Schedule {
+        enabled: true,
+        ..Schedule::default()
+      },
+      ..TimerConfig::default()
+    };
+    let clock = FakeClock::new(monday_7am);
+    let mut timer = Timer::new(config, clock.clone());
+    run(&mut timer, &clock, 1);
+    assert!(timer.is_off_hours());
+    assert!(!timer.pause(None));
+    assert!(!timer.pause_until_next_workday());
+    assert!(!timer.is_paused());
+  }
+
+  #[test]
+  fn reset_restarts_work_only_while_counting() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 300);
+    timer.pause(None);
+    assert!(timer.reset());
+    assert_eq!(timer.elapsed_secs(), 0);
+    assert!(!timer.is_paused());
+    assert_eq!(timer.cycle(), 1);
+
+    assert_eq!(
+      run(&mut timer, &clock, 600),
+      Some(Transition::EnterBlocking)
+    );
+    run(&mut timer, &clock, 30);
+    assert!(!timer.reset());
+    assert_eq!(timer.phase(), Phase::Blocking);
+    assert_eq!(timer.elapsed_secs(), 30);
+  }
+
+  #[test]
+  fn escape_finishes_the_cycle() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    assert_eq!(timer.escape(), None);
+    run(&mut timer, &clock, 600);
+    run(&mut timer, &clock, 30);
+    assert_eq!(timer.escape(), Some(Transition::EnterCounting));
+    assert_eq!(timer.phase(), Phase::Counting);
+    assert_eq!(timer.cycle(), 2);
+    assert_eq!(timer.elapsed_secs(), 0);
+  }
+
+  #[test]
+  fn strict_mode_blocks_escape() {
+    let config = TimerConfig {
+      strict_mode: true,
+      ..TimerConfig::default()
+    };
+    let (mut timer, clock) = timer(config);
+    run(&mut timer, &clock, 600);
+    assert!(timer.is_strict_break());
+    assert_eq!(timer.escape(), None);
+    assert!(timer.postpone().is_err());
+    assert_eq!(timer.phase(), Phase::Blocking);
+  }
+
+  #[test]
+  fn late_frame_past_rest_does_not_underflow() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 600);
+    run(&mut timer, &clock, 100);
+    // 界面卡住很久，下一帧已经远远超过休息时长
+    clock.advance(10 * 60 * 1000);
+    timer.apply_config(TimerConfig::default());
+    assert_eq!(timer.phase(), Phase::Blocking);
+    assert_eq!(timer.remaining_secs(), 0);
+    assert_eq!(timer.tick(), Some(Transition::BlockingEnd));
+    assert_eq!(timer.remaining_secs(), 0);
+  }
+
+  #[test]
+  fn postpone_limits() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    assert_eq!(timer.postpone(), Err("距离休息还早，无需推迟"));
+    run(&mut timer, &clock, 400);
+    assert!(timer.can_postpone());
+    assert_eq!(timer.postpone(), Ok(None));
+    assert_eq!(timer.remaining_secs(), 300);
+    assert_eq!(timer.postpones_left(), 0);
+    assert_eq!(timer.postpone(), Err("推迟次数已用完"));
+
+    assert_eq!(
+      run(&mut timer, &clock, 300),
+      Some(Transition::EnterBlocking)
+    );
+    run(&mut timer, &clock, 120);
+    timer.unlock();
+    assert_eq!(timer.postpones_left(), 1);
+    let limited = Timer::new(TimerConfig::default(), clock.clone()).with_postponed_today(3);
+    assert_eq!(limited.postpones_left(), 0);
+  }
+
+  #[test]
+  fn postpone_during_break_uses_pending_config() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 600);
+    let changed = TimerConfig {
+      work_secs: 1200,
+      ..TimerConfig::default()
+    };
+    timer.apply_config(changed);
+    assert_eq!(timer.postpone(), Ok(Some(Transition::EnterCounting)));
+    assert_eq!(timer.phase(), Phase::Counting);
+    assert_eq!(timer.config().work_secs, 1200);
+    assert_eq!(timer.remaining_secs(), 300);
+    assert_eq!(timer.cycle(), 1);
+  }
+
+  #[test]
+  fn deferred_break_is_not_a_warning() {
+    let config = TimerConfig {
+      warning_secs: 60,
+      ..TimerConfig::default()
+    };
+    let (mut timer, clock) = timer(config);
+    timer.report_busy(Some(DeferReason::Fullscreen));
+    assert_eq!(run(&mut timer, &clock, 600), Some(Transition::BreakWarning));
+    assert!(timer.is_warning());
+    assert_eq!(run(&mut timer, &clock, 60), Some(Transition::BreakDeferred));
+    assert_eq!(timer.deferred_by(), Some(DeferReason::Fullscreen));
+    assert!(!timer.is_warning());
+    assert_eq!(run(&mut timer, &clock, 100), None);
+    assert!(!timer.is_warning());
+
+    // 暂缓超过上限后无论如何都开始休息
+    assert_eq!(
+      run(&mut timer, &clock, 300),
+      Some(Transition::EnterBlocking)
+    );
+    assert_eq!(timer.deferred_by(), None);
+  }
+
+  #[test]
+  fn sleep_counts_as_break() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 300);
+    // 较短的休眠相当于暂停
+    clock.sleep(30 * 1000);
+    assert_eq!(run(&mut timer, &clock, 1), None);
+    assert_eq!(timer.elapsed_secs(), 301);
+
+    clock.sleep(5 * 60 * 1000);
+    assert_eq!(timer.tick(), Some(Transition::BreakCredited));
+    assert_eq!(timer.cycle(), 2);
+    assert_eq!(timer.elapsed_secs(), 0);
+  }
+
+  #[test]
+  fn last_work_counts_only_worked_time() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 100);
+    timer.pause(None);
+    run(&mut timer, &clock, 50);
+    timer.resume();
+    run(&mut timer, &clock, 200);
+    clock.sleep(30 * 1000);
+    run(&mut timer, &clock, 100);
+    // 推迟会调回进度，但不影响实际工作的时长
+    assert_eq!(timer.postpone(), Ok(None));
+    assert_eq!(
+      run(&mut timer, &clock, 300),
+      Some(Transition::EnterBlocking)
+    );
+    assert_eq!(
+      timer.last_work(),
+      WorkSpan {
+        worked_secs: 700,
+        longest_stretch_secs: 400,
+      }
+    );
+
+    // 休息不会改变上一段工作
+    run(&mut timer, &clock, 120);
+    timer.unlock();
+    assert_eq!(timer.last_work().worked_secs, 700);
+    run(&mut timer, &clock, 30);
+    assert!(timer.reset());
+    assert_eq!(timer.last_work().worked_secs, 30);
+  }
+
+  #[test]
+  fn short_absence_does_not_skip_long_break() {
+    let config = TimerConfig {
+      long_rest_every: 2,
+      ..TimerConfig::default()
+    };
+    let (mut timer, clock) = timer(config);
+    run(&mut timer, &clock, 600);
+    run(&mut timer, &clock, 120);
+    timer.unlock();
+    assert!(timer.is_long_break());
+    run(&mut timer, &clock, 100);
+
+    // 离开的时间够一次普通休息，但不够长休息
+    clock.sleep(3 * 60 * 1000);
+    assert_eq!(run(&mut timer, &clock, 1), None);
+    assert_eq!(timer.report_idle(200), None);
+    timer.report_idle(0);
+    assert_eq!(timer.cycle(), 2);
+    assert!(timer.is_long_break());
+
+    clock.sleep(15 * 60 * 1000);
+    assert_eq!(timer.tick(), Some(Transition::BreakCredited));
+    assert_eq!(timer.cycle(), 1);
+  }
+
+  #[test]
+  fn long_absence_counts_as_long_break() {
+    let config = TimerConfig {
+      long_rest_every: 4,
+      ..TimerConfig::default()
+    };
+    let (mut timer, clock) = timer(config);
+    run(&mut timer, &clock, 600);
+    run(&mut timer, &clock, 120);
+    timer.unlock();
+    assert_eq!(timer.cycle(), 2);
+    assert_eq!(timer.report_idle(900), Some(Transition::BreakCredited));
+    assert_eq!(timer.cycle(), 1);
+  }
+
+  #[test]
+  fn wall_clock_changes_are_not_sleep() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 300);
+    // 系统时间向前跳了一个小时，没有休眠
+    clock.set_wall(START_MS + 3600 * 1000);
+    assert_eq!(run(&mut timer, &clock, 1), None);
+    assert_eq!(timer.cycle(), 1);
+    assert_eq!(timer.elapsed_secs(), 301);
+    // 向后调整也不影响计时
+    clock.set_wall(START_MS);
+    assert_eq!(run(&mut timer, &clock, 1), None);
+    assert_eq!(timer.elapsed_secs(), 302);
+  }
+
+  #[test]
+  fn apply_config_keeps_progress() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 300);
+    let longer = TimerConfig {
+      work_secs: 1200,
+      ..TimerConfig::default()
+    };
+    timer.apply_config(longer);
+    assert_eq!(timer.elapsed_secs(), 600);
+    assert_eq!(timer.remaining_secs(), 600);
+
+    // 缩短工作时长不会立即锁屏
+    let shorter = TimerConfig {
+      work_secs: 60,
+      ..TimerConfig::default()
+    };
+    timer.apply_config(shorter);
+    assert_eq!(timer.elapsed_secs(), 30);
+    assert_eq!(timer.phase(), Phase::Counting);
+  }
+
+  #[test]
+  fn apply_config_during_break_waits_for_next_work() {
+    let (mut timer, clock) = timer(TimerConfig::default());
+    run(&mut timer, &clock, 600);
+    let changed = TimerConfig {
+      work_secs: 1200,
+      rest_secs: 300,
+      ..TimerConfig::default()
+    };
+    timer.apply_config(changed);
+    assert_eq!(timer.remaining_secs(), 120);
+    assert_eq!(run(&mut timer, &clock, 120), Some(Transition::BlockingEnd));
+    timer.unlock();
+    assert_eq!(timer.config().work_secs, 1200);
+    assert_eq!(timer.remaining_secs(), 1200);
+  }
+}
@@ -1,35 +1,230 @@
+use std::{sync::mpsc::RecvTimeoutError, time::Duration};
+
 use tauri::{
-  App, Manager,
-  menu::{Menu, MenuItem},
+  App, AppHandle, Manager, Runtime,
+  menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
   tray::TrayIconBuilder,
 };
 
 use crate::{
   constant::APP_TITLE,
-  window_counter::{
-    CounterEventSignal, EVENT_PAUSE_COUNTING, EVENT_RESET_COUNTING, EVENT_RESUME_COUNTING,
-  },
+  export::{ExportFormat, export_recent_history},
+  message::{Command, CounterBus, CounterBusState, CounterStatus},
+  progress_icon::ProgressIcon,
+  timer::Phase,
   window_main::open_main_window,
 };
 
+const TRAY_MENU_STATUS: &'static str = "status";
 const TRAY_MENU_QUIT: &'static str = "quit";
 const TRAY_MENU_RESET: &'static str = "reset";
 const TRAY_MENU_PAUSE: &'static str = "puase";
-const TRAY_MENU_RESUME: &'static str = "resume";
+const TRAY_MENU_PAUSE_30M: &'static str = "pause_30m";
+const TRAY_MENU_PAUSE_1H: &'static str = "pause_1h";
+const TRAY_MENU_PAUSE_NEXT_WORKDAY: &'static str = "pause_next_workday";
+const TRAY_MENU_BREAK_NOW: &'static str = "break_now";
+const TRAY_MENU_POSTPONE: &'static str = "postpone";
 const TRAY_MENU_SETTING: &'static str = "setting";
+const TRAY_MENU_EXPORT_CSV: &'static str = "export_csv";
+const TRAY_MENU_EXPORT_JSON: &'static str = "export_json";
+const TRAY_MENU_EXPORT_ICS: &'static str = "export_ics";
+
+/// 严格模式下休息期间不可用的菜单项
+const TRAY_MENU_SKIPS_BREAK: [&'static str; 7] = [
+  TRAY_MENU_QUIT,
+  TRAY_MENU_RESET,
+  TRAY_MENU_PAUSE,
+  TRAY_MENU_PAUSE_30M,
+  TRAY_MENU_PAUSE_1H,
+  TRAY_MENU_PAUSE_NEXT_WORKDAY,
+  TRAY_MENU_POSTPONE,
+];
+
+/// 托盘提示和菜单顶部显示的当前状态，例如“工作中 · 12 分钟后休息”
+fn status_text(status: &CounterStatus) -> String {
+  let minutes = status.remaining_secs.div_ceil(60);
+  if status.off_hours {
+    return "非工作时间".into();
+  }
+  match status.phase {
+    Phase::Counting if status.paused => match status.pause_remaining_secs {
+      Some(secs) if secs >= 3600 => format!(
+        "已暂停 · {} 小时 {} 分钟后继续",
+        secs / 3600,
+        secs % 3600 / 60
+      ),
+      Some(secs) => format!("已暂停 · {} 分钟后继续", secs.div_ceil(60)),
+      None => "已暂停".into(),
+    },
+    Phase::Counting if status.deferred_by.is_some() => "工作中 · 等待休息".into(),
+    Phase::Counting if status.micro_break => "护眼休息中".into(),
+    Phase::Counting if status.remaining_secs < 60 => "工作中 · 即将休息".into(),
+    Phase::Counting => format!("工作中 · {} 分钟后休息", minutes),
+    Phase::Blocking if status.long_break => format!("长休息中 · 还剩 {} 分钟", minutes),
+    Phase::Blocking => format!("休息中 · 还剩 {} 分钟", minutes),
+    Phase::BlockEnd => "休息结束".into(),
+  }
+}
+
+/// 托盘菜单各项当前是否可用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MenuState {
+  paused: bool,
+  can_pause: bool,
+  /// 定时暂停可以在已暂停时修改暂停时长
+  can_timed_pause: bool,
+  /// 超过暂停上限的定时暂停不可用，避免被悄悄缩短
+  can_pause_30m: bool,
+  can_pause_1h: bool,
+  can_pause_next_workday: bool,
+  can_break_now: bool,
+  can_postpone: bool,
+  can_reset: bool,
+  can_quit: bool,
+}
+
+impl MenuState {
+  fn from_status(status: &CounterStatus) -> Self {
+    let working = matches!(status.phase, Phase::Counting) && !status.off_hours;
+    let within_limit = |secs: Option<u32>| match (secs, status.pause_limit_secs) {
+      (_, None) => true,
+      (Some(secs), Some(max)) => secs <= max,
+      (None, Some(_)) => false,
+    };
+    Self {
+      paused: status.paused,
+      // 已经暂停时总是可以继续
+      can_pause: working || status.paused,
+      can_timed_pause: working,
+      can_pause_30m: within_limit(Some(30 * 60)),
+      can_pause_1h: within_limit(Some(60 * 60)),
+      can_pause_next_workday: within_limit(status.next_workday_secs),
+      can_break_now: working,
+      can_postpone: status.can_postpone && !status.off_hours,
+      // 休息期间重置不生效
+      can_reset: working,
+      can_quit: !status.strict_break,
+    }
+  }
+}
+
+/// 刷新托盘状态文字和进度环的间隔
+const TRAY_STATUS_REFRESH: Duration = Duration::from_secs(5);
+
+fn bus_status<R: Runtime>(app: &AppHandle<R>) -> CounterStatus {
+  app.state::<CounterBusState>().status()
+}
 
-pub fn setup_tray(app: &mut App) {
+/// 严格模式下的休息中，托盘不能用来跳过休息
+fn is_strict_break<R: Runtime>(app: &AppHandle<R>) -> bool {
+  bus_status(app).strict_break
+}
+
+pub fn setup_tray(app: &mut App, bus: &CounterBus) {
+  let icon = ProgressIcon::from_status(&bus.status());
+  let status = status_text(&bus.status());
+  let menu_state = MenuState::from_status(&bus.status());
+  let status_i = MenuItem::with_id(app, TRAY_MENU_STATUS, &status, false, None::<&str>).unwrap();
   let quit_i = MenuItem::with_id(app, TRAY_MENU_QUIT, "退出", true, None::<&str>).unwrap();
-  let reset_i = MenuItem::with_id(app, TRAY_MENU_RESET, "重置", true, None::<&str>).unwrap();
-  let pause_i = MenuItem::with_id(app, TRAY_MENU_PAUSE, "暂停", true, None::<&str>).unwrap();
-  let resume_i = MenuItem::with_id(app, TRAY_MENU_RESUME, "继续", true, None::<&str>).unwrap();
+  let reset_i = MenuItem::with_id(
+    app,
+    TRAY_MENU_RESET,
+    "重置",
+    menu_state.can_reset,
+    None::<&str>,
+  )
+  .unwrap();
+  let pause_i = CheckMenuItem::with_id(
+    app,
+    TRAY_MENU_PAUSE,
+    "暂停",
+    menu_state.can_pause,
+    menu_state.paused,
+    None::<&str>,
+  )
+  .unwrap();
+  let pause_30m_i = MenuItem::with_id(
+    app,
+    TRAY_MENU_PAUSE_30M,
+    "30 分钟",
+    menu_state.can_pause_30m,
+    None::<&str>,
+  )
+  .unwrap();
+  let pause_1h_i = MenuItem::with_id(
+    app,
+    TRAY_MENU_PAUSE_1H,
+    "1 小时",
+    menu_state.can_pause_1h,
+    None::<&str>,
+  )
+  .unwrap();
+  let pause_next_workday_i = MenuItem::with_id(
+    app,
+    TRAY_MENU_PAUSE_NEXT_WORKDAY,
+    "到下个工作日",
+    menu_state.can_pause_next_workday,
+    None::<&str>,
+  )
+  .unwrap();
+  let timed_pause_i = Submenu::with_items(
+    app,
+    "定时暂停",
+    menu_state.can_timed_pause,
+    &[&pause_30m_i, &pause_1h_i, &pause_next_workday_i],
+  )
+  .unwrap();
+  let break_now_i = MenuItem::with_id(
+    app,
+    TRAY_MENU_BREAK_NOW,
+    "立即休息",
+    menu_state.can_break_now,
+    None::<&str>,
+  )
+  .unwrap();
+  let postpone_i = MenuItem::with_id(
+    app,
+    TRAY_MENU_POSTPONE,
+    "推迟休息",
+    menu_state.can_postpone,
+    None::<&str>,
+  )
+  .unwrap();
   let setting_i = MenuItem::with_id(app, TRAY_MENU_SETTING, "设置", true, None::<&str>).unwrap();
+  let export_i = Submenu::with_items(
+    app,
+    "导出最近 30 天历史",
+    true,
+    &[
+      &MenuItem::with_id(app, TRAY_MENU_EXPORT_CSV, "CSV", true, None::<&str>).unwrap(),
+      &MenuItem::with_id(app, TRAY_MENU_EXPORT_JSON, "JSON", true, None::<&str>).unwrap(),
+      &MenuItem::with_id(app, TRAY_MENU_EXPORT_ICS, "iCalendar", true, None::<&str>).unwrap(),
+    ],
+  )
+  .unwrap();
 
-  let menu = Menu::with_items(app, &[&pause_i, &resume_i, &reset_i, &setting_i, &quit_i]).unwrap();
-  let _ = TrayIconBuilder::new()
-    .icon(app.default_window_icon().unwrap().clone())
+  let menu = Menu::with_items(
+    app,
+    &[
+      &status_i,
+      &PredefinedMenuItem::separator(app).unwrap(),
+      &pause_i,
+      &timed_pause_i,
+      &break_now_i,
+      &postpone_i,
+      &reset_i,
+      &PredefinedMenuItem::separator(app).unwrap(),
+      &setting_i,
+      &export_i,
+      &quit_i,
+    ],
+  )
+  .unwrap();
+  let tray = TrayIconBuilder::new()
+    .icon(icon.render())
     .menu(&menu)
-    .tooltip(APP_TITLE)
+    .tooltip(format!("{}\n{}", APP_TITLE, status))
+    .title(&status)
     .show_menu_on_left_click(true)
     // .on_tray_icon_event(|ic, event| {
     //   use tauri::tray::TrayIconEvent;
@@ -41,31 +236,95 @@ pub fn setup_tray(app: &mut App) {
     //   }
     // })
     .on_menu_event(move |app, event| match event.id.as_ref() {
+      id if TRAY_MENU_SKIPS_BREAK.contains(&id) && is_strict_break(app) => {}
       TRAY_MENU_QUIT => {
         app.exit(0);
       }
       TRAY_MENU_PAUSE => {
-        app
-          .state::<CounterEventSignal>()
-          .store(EVENT_PAUSE_COUNTING, std::sync::atomic::Ordering::Relaxed);
+        let command = if bus_status(app).paused {
+          Command::Resume
+        } else {
+          Command::Pause(None)
+        };
+        let _ = app.state::<CounterBusState>().send(command);
+      }
+      TRAY_MENU_PAUSE_30M => {
+        let _ = app
+          .state::<CounterBusState>()
+          .send(Command::Pause(Some(30 * 60)));
+      }
+      TRAY_MENU_PAUSE_1H => {
+        let _ = app
+          .state::<CounterBusState>()
+          .send(Command::Pause(Some(60 * 60)));
+      }
+      TRAY_MENU_PAUSE_NEXT_WORKDAY => {
+        let _ = app
+          .state::<CounterBusState>()
+          .send(Command::PauseUntilNextWorkday);
       }
-      TRAY_MENU_RESUME => {
-        app
-          .state::<CounterEventSignal>()
-          .store(EVENT_RESUME_COUNTING, std::sync::atomic::Ordering::Relaxed);
+      TRAY_MENU_BREAK_NOW => {
+        let _ = app.state::<CounterBusState>().send(Command::BreakNow);
+      }
+      TRAY_MENU_POSTPONE => {
+        let _ = app.state::<CounterBusState>().send(Command::Postpone);
       }
       TRAY_MENU_SETTING => {
         open_main_window(app);
       }
       TRAY_MENU_RESET => {
-        app
-          .state::<CounterEventSignal>()
-          .store(EVENT_RESET_COUNTING, std::sync::atomic::Ordering::Relaxed);
+        let _ = app.state::<CounterBusState>().send(Command::Reset);
       }
+      TRAY_MENU_EXPORT_CSV => export_recent_history(app, ExportFormat::Csv),
+      TRAY_MENU_EXPORT_JSON => export_recent_history(app, ExportFormat::Json),
+      TRAY_MENU_EXPORT_ICS => export_recent_history(app, ExportFormat::Ics),
       _ => {
         println!("menu item {:?} not handled", event.id);
       }
     })
     .build(app)
     .unwrap();
+
+  // 更新托盘上显示的状态，并根据状态启用或禁用菜单项
+  let app = app.handle().clone();
+  let events = bus.subscribe();
+  std::thread::spawn(move || {
+    let mut shown_menu = menu_state;
+    let mut shown = status;
+    let mut shown_icon = icon;
+    loop {
+      let (status, changed) = match events.recv_timeout(TRAY_STATUS_REFRESH) {
+        Ok(event) => (event.status, true),
+        Err(RecvTimeoutError::Timeout) => (bus_status(&app), false),
+        Err(RecvTimeoutError::Disconnected) => break,
+      };
+      let menu_state = MenuState::from_status(&status);
+      // 点击勾选项时系统会自动切换勾选状态，收到事件后总是按实际状态重新设置
+      if changed || menu_state != shown_menu {
+        let _ = pause_i.set_checked(menu_state.paused);
+        let _ = pause_i.set_enabled(menu_state.can_pause);
+        let _ = timed_pause_i.set_enabled(menu_state.can_timed_pause);
+        let _ = pause_30m_i.set_enabled(menu_state.can_pause_30m);
+        let _ = pause_1h_i.set_enabled(menu_state.can_pause_1h);
+        let _ = pause_next_workday_i.set_enabled(menu_state.can_pause_next_workday);
+        let _ = break_now_i.set_enabled(menu_state.can_break_now);
+        let _ = postpone_i.set_enabled(menu_state.can_postpone);
+        let _ = reset_i.set_enabled(menu_state.can_reset);
+        let _ = quit_i.set_enabled(menu_state.can_quit);
+        shown_menu = menu_state;
+      }
+      let text = status_text(&status);
+      if text != shown {
+        let _ = status_i.set_text(&text);
+        let _ = tray.set_tooltip(Some(format!("{}\n{}", APP_TITLE, text)));
+        let _ = tray.set_title(Some(&text));
+        shown = text;
+      }
+      let icon = ProgressIcon::from_status(&status);
+      if icon != shown_icon {
+        let _ = tray.set_icon(Some(icon.render()));
+        shown_icon = icon;
+      }
+    }
+  });
 }
@@ -1,113 +1,121 @@
 use std::{
-  sync::{Arc, atomic::AtomicU8},
-  time::{SystemTime, UNIX_EPOCH},
+  collections::HashMap,
+  sync::mpsc::Receiver,
+  time::{Duration, Instant},
 };
 
-use display_info::DisplayInfo;
 use eframe::egui::{
-  CentralPanel, Color32, Frame, IconData, Id, Key, Pos2, RichText, Sense, Vec2, ViewportBuilder,
-  ViewportCommand, ViewportId, Visuals,
+  Align2, CentralPanel, Color32, FontId, Frame, IconData, Id, Pos2, RichText, Sense, Vec2,
+  ViewportBuilder, ViewportCommand, ViewportId, Visuals,
 };
 
 #[cfg(windows)]
 use winit::platform::windows::EventLoopBuilderExtWindows;
 
-use crate::{chinese_font::setup_fonts, settings::Settings};
+use crate::{
+  chinese_font::setup_fonts,
+  display::DisplayLayout,
+  escape::{Escape, EscapeInput, escape_from_settings, submit_field},
+  fullscreen::{FullscreenDetector, system_fullscreen_detector},
+  idle::{IdleSource, system_idle_source},
+  message::{Command, CounterBusState, CounterStatus, Event, EventKind},
+  process::{ProcessSource, find_running, system_process_source},
+  settings::{EscapeMethod, Settings},
+  timer::{DeferReason, MonotonicClock, Phase, Timer, TimerConfig, Transition},
+};
 
-const COUNTER_WINDOW_WIDTH: f32 = 142f32;
-const COUNTER_WINDOW_HEIGHT: f32 = 70f32;
 const COUNTER_WINDOW_RADIUS: f32 = 24f32;
 const COUNTER_WINDOW_FONT_SIZE: f32 = 40f32;
-
-#[derive(Debug, Clone, Copy)]
-enum State {
-  Counting,
-  Blocking,
-  BlockEnd,
-}
-
-const EVENT_ENTER_BLOCKING: u8 = 1;
-const EVENT_ENTER_COUNTING: u8 = 2;
-const EVENT_BLOCKING_END: u8 = 3;
-pub const EVENT_PAUSE_COUNTING: u8 = 4;
-pub const EVENT_RESUME_COUNTING: u8 = 5;
-pub const EVENT_RESET_COUNTING: u8 = 6;
-
-pub type CounterEventSignal = Arc<AtomicU8>;
+/// 空闲检测的间隔，Linux 下可能需要启动 loginctl 进程，不宜每帧都查
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// 检测是否需要暂缓休息的间隔
+const BUSY_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// 距离休息还剩多少秒时开始检测是否需要暂缓
+const BUSY_POLL_AHEAD_SECS: u32 = 10;
+/// 重新读取屏幕布局的间隔
+const LAYOUT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+/// 休息结束后鼠标离开原来的位置超过这个距离才解锁，避免轻微抖动就解锁
+const UNLOCK_DRIFT: f32 = 10.0;
 
 struct CounterApp {
-  work_secs: u32,
-  rest_secs: u32,
-  escape_count: u32,
-  count_start_time: u64,
-  count_paused_time: Option<u64>,
-  state: State,
-  display: CounterDisplay,
-  second_display: Option<CounterDisplay2>,
-  event_signal: Arc<AtomicU8>,
-  mouse_pos: (u32, u32),
-  escape_pressed_count: u32,
-}
-
-struct CounterDisplay {
-  scale: f32,
-  /// width, height
-  screen_size: (f32, f32),
-  /// left, top, width, height
-  counting_rect: ((f32, f32), (f32, f32)),
+  timer: Timer<MonotonicClock>,
+  /// 待应用到窗口上的阶段切换，在下一帧开始时处理
+  transition: Option<Transition>,
+  layout: DisplayLayout,
+  layout_polled_at: Instant,
+  bus: CounterBusState,
+  commands: Receiver<Command>,
+  idle_source: Box<dyn IdleSource + Send>,
+  idle_polled_at: Instant,
+  fullscreen: Box<dyn FullscreenDetector + Send>,
+  processes: Box<dyn ProcessSource + Send>,
+  /// 运行时暂缓休息的程序
+  defer_processes: Vec<String>,
+  /// 导致暂缓休息的程序
+  busy_process: Option<String>,
+  busy_polled_at: Instant,
+  /// 休息结束后每个遮挡窗口里鼠标最初的位置，鼠标不在窗口里时为 None
+  unlock_anchors: HashMap<ViewportId, Option<Pos2>>,
+  escape: Box<dyn Escape + Send>,
+  escape_method: EscapeMethod,
+  /// 已完成紧急退出操作，正在填写退出原因
+  escape_reason: Option<String>,
+  /// 上一帧其它屏幕上的 Escape 状态
+  other_escape: EscapeInput,
 }
 
-struct CounterDisplay2 {
-  scale: f32,
-  screen_rect: ((f32, f32), (f32, f32)),
+fn fmt_count(minutes: u32, seconds: u32, long_break: bool) -> String {
+  format!(
+    "{}中，{:02}:{:02} 后解锁",
+    if long_break { "长休息" } else { "休息" },
+    minutes,
+    seconds
+  )
 }
 
-fn fmt_count(minutes: u32, seconds: u32) -> String {
-  format!("休息中，{:02}:{:02} 后解锁", minutes, seconds)
+/// 定时暂停的剩余时间，超过一小时只显示到分钟
+fn fmt_pause(secs: u32) -> String {
+  if secs >= 3600 {
+    format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+  } else {
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+  }
 }
 
-fn now() -> u64 {
-  SystemTime::now()
-    .duration_since(UNIX_EPOCH)
-    .unwrap()
-    .as_secs()
+/// 随时间明暗变化的背景色，用于提醒
+fn pulse_color(ctx: &eframe::egui::Context, (r, g, b): (u8, u8, u8)) -> Color32 {
+  let t = ctx.input(|i| i.time) as f32;
+  let alpha = 150.0 + 70.0 * (t * std::f32::consts::PI).sin();
+  Color32::from_rgba_unmultiplied(r, g, b, alpha as u8)
 }
 
 impl CounterApp {
-  pub fn new(settings: &Settings, event_signal: Arc<AtomicU8>) -> Self {
-    let displays = DisplayInfo::all().unwrap();
-    let primary_display = displays.iter().find(|d| d.is_primary).unwrap();
-    let scale = primary_display.scale_factor;
-    let screen_width = (primary_display.width as f32) / scale;
-    let screen_height = (primary_display.height as f32) / scale;
-
-    let width = COUNTER_WINDOW_WIDTH / scale;
-    let height = COUNTER_WINDOW_HEIGHT / scale;
-    let bottom = 80f32 / scale;
-    let right = 30f32 / scale;
-
+  pub fn new(
+    settings: &Settings,
+    postponed_today: u32,
+    bus: CounterBusState,
+    commands: Receiver<Command>,
+  ) -> Self {
     Self {
-      display: CounterDisplay {
-        scale: scale,
-        screen_size: (screen_width - 1.0, screen_height - 1.0),
-        counting_rect: (
-          (
-            screen_width - width - right,
-            screen_height - bottom - height,
-          ),
-          (width, height),
-        ),
-      },
-      second_display: None,
-      work_secs: settings.work_secs,
-      rest_secs: settings.rest_secs,
-      escape_count: settings.escape_count,
-      state: State::Counting,
-      count_start_time: now(),
-      count_paused_time: None,
-      event_signal,
-      mouse_pos: (0, 0),
-      escape_pressed_count: 0,
+      layout: DisplayLayout::query().expect("no display found"),
+      layout_polled_at: Instant::now(),
+      timer: Timer::new(TimerConfig::from(settings), MonotonicClock::default())
+        .with_postponed_today(postponed_today),
+      transition: None,
+      bus,
+      commands,
+      idle_source: system_idle_source(),
+      idle_polled_at: Instant::now(),
+      fullscreen: system_fullscreen_detector(),
+      processes: system_process_source(),
+      defer_processes: settings.defer_processes.clone(),
+      busy_process: None,
+      busy_polled_at: Instant::now(),
+      unlock_anchors: HashMap::new(),
+      escape: escape_from_settings(settings),
+      escape_method: settings.escape_method,
+      escape_reason: None,
+      other_escape: EscapeInput::default(),
     }
   }
   fn place_window(&self, ctx: &eframe::egui::Context, pos: Pos2, size: Vec2) {
@@ -117,90 +125,218 @@ impl CounterApp {
       eframe::egui::WindowLevel::AlwaysOnTop,
     ));
   }
-  fn handle_event(&mut self, ctx: &eframe::egui::Context) -> bool {
-    let evt = self.event_signal.load(std::sync::atomic::Ordering::Relaxed);
-    self
-      .event_signal
-      .store(0, std::sync::atomic::Ordering::Relaxed);
-    match evt {
-      EVENT_PAUSE_COUNTING => {
-        self.count_paused_time = Some(now());
-        false
-      }
-      EVENT_RESUME_COUNTING => {
-        self.count_paused_time.take().map(|v| {
-          let pasued_secs = now() - v;
-          self.count_start_time += pasued_secs;
-        });
-        false
-      }
-      EVENT_RESET_COUNTING => {
-        self.count_start_time = now();
-        self.count_paused_time = None;
-        false
+  /// 按当前阶段把主窗口放到计时小窗或主屏幕全屏的位置
+  fn place_for_phase(&self, ctx: &eframe::egui::Context) {
+    let rect = if matches!(self.timer.phase(), Phase::Counting) {
+      self.layout.primary.counting_rect
+    } else {
+      self.layout.primary.screen_rect
+    };
+    self.place_window(ctx, rect.0.into(), rect.1.into());
+  }
+  /// 定期重新读取屏幕布局，发生变化时（插拔显示器、修改分辨率或缩放）重新摆放窗口
+  fn poll_layout(&mut self, ctx: &eframe::egui::Context) {
+    if self.layout_polled_at.elapsed() < LAYOUT_POLL_INTERVAL {
+      return;
+    }
+    self.layout_polled_at = Instant::now();
+    if let Some(layout) = DisplayLayout::query()
+      && layout != self.layout
+    {
+      self.layout = layout;
+      self.place_for_phase(ctx);
+    }
+  }
+  fn status(&self) -> CounterStatus {
+    CounterStatus {
+      phase: self.timer.phase(),
+      paused: self.timer.is_paused(),
+      pause_remaining_secs: self.timer.pause_remaining_secs(),
+      pause_limit_secs: self.timer.pause_limit_secs(),
+      next_workday_secs: self.timer.next_workday_in_secs(),
+      cycle: self.timer.cycle(),
+      long_break: self.timer.is_long_break(),
+      micro_break: self.timer.micro_break_remaining_secs().is_some(),
+      warning: self.timer.is_warning(),
+      postpones_left: self.timer.postpones_left(),
+      can_postpone: self.timer.can_postpone(),
+      strict_break: self.timer.is_strict_break(),
+      off_hours: self.timer.is_off_hours(),
+      idle: self.timer.is_idle(),
+      deferred_by: self.timer.deferred_by(),
+      elapsed_secs: self.timer.elapsed_secs(),
+      remaining_secs: self.timer.remaining_secs(),
+      last_work: self.timer.last_work(),
+    }
+  }
+
+  fn publish(&self, kind: EventKind) {
+    self.bus.publish(Event {
+      kind,
+      status: self.status(),
+    });
+  }
+
+  /// 推迟休息，从休息中推迟时会切回计时小窗
+  fn postpone(&mut self) -> EventKind {
+    match self.timer.postpone() {
+      Ok(t) => {
+        if t.is_some() {
+          self.transition = t;
+        }
+        EventKind::Postponed
       }
-      EVENT_ENTER_BLOCKING => {
-        self.place_window(ctx, (0f32, 0f32).into(), self.display.screen_size.into());
-        self.state = State::Blocking;
-        self.count_start_time = now();
-        self.escape_pressed_count = 0;
-        let second_display = DisplayInfo::all()
-          .unwrap_or_default()
-          .iter()
-          .find(|d| !d.is_primary)
-          .map(|d| {
-            let scale = d.scale_factor;
-            let screen_width = (d.width as f32) / scale;
-            let screen_height = (d.height as f32) / scale;
-            // println!("{}, {} {}", scale, screen_width, screen_height);
-            return CounterDisplay2 {
-              scale,
-              screen_rect: (
-                ((d.x as f32) / scale, (d.y as f32) / scale),
-                (screen_width, screen_height),
-              ),
-            };
-          });
-        self.second_display = second_display;
+      Err(_) => EventKind::PostponeRejected,
+    }
+  }
+
+  /// 处理托盘和主窗口发来的指令，一帧内收到的指令按顺序全部执行
+  fn handle_commands(&mut self) {
+    while let Ok(command) = self.commands.try_recv() {
+      let kind = match command {
+        Command::Pause(secs) => {
+          if !self.timer.pause(secs) {
+            continue;
+          }
+          EventKind::Paused
+        }
+        Command::PauseUntilNextWorkday => {
+          if !self.timer.pause_until_next_workday() {
+            continue;
+          }
+          EventKind::Paused
+        }
+        Command::Resume => {
+          self.timer.resume();
+          EventKind::Resumed
+        }
+        Command::Reset => {
+          if !self.timer.reset() {
+            continue;
+          }
+          EventKind::Reset
+        }
+        Command::Postpone => self.postpone(),
+        Command::BreakNow => {
+          // 和其它阶段切换一样在 update 中处理
+          if let Some(t) = self.timer.break_now() {
+            self.transition = Some(t);
+          }
+          continue;
+        }
+        Command::ApplySettings(settings) => {
+          self.timer.apply_config(TimerConfig::from(&settings));
+          self.escape = escape_from_settings(&settings);
+          self.escape_method = settings.escape_method;
+          self.escape_reason = None;
+          self.defer_processes = settings.defer_processes;
+          EventKind::SettingsApplied
+        }
+      };
+      self.publish(kind);
+    }
+  }
 
+  /// 根据阶段切换调整窗口，返回是否需要跳过本帧的绘制
+  fn apply_transition(&mut self, ctx: &eframe::egui::Context, transition: Transition) -> bool {
+    self.publish(match transition {
+      Transition::BreakCredited => EventKind::BreakCredited,
+      Transition::BreakWarning => EventKind::BreakWarning,
+      Transition::MicroBreakStart => EventKind::MicroBreakStarted,
+      Transition::MicroBreakEnd => EventKind::MicroBreakEnded,
+      Transition::WorkHoursStart => EventKind::WorkHoursStarted,
+      Transition::WorkHoursEnd => EventKind::WorkHoursEnded,
+      Transition::BreakDeferred => EventKind::BreakDeferred {
+        process: self.busy_process.clone(),
+      },
+      _ => EventKind::PhaseChanged,
+    });
+    match transition {
+      Transition::EnterBlocking => {
+        self.escape.reset();
+        self.escape_reason = None;
+        if let Some(layout) = DisplayLayout::query() {
+          self.layout = layout;
+        }
+        self.place_for_phase(ctx);
         true
       }
-      EVENT_ENTER_COUNTING => {
-        self.second_display.take();
-        self.place_window(
-          ctx,
-          self.display.counting_rect.0.into(),
-          self.display.counting_rect.1.into(),
-        );
-
-        self.state = State::Counting;
-        self.count_start_time = now();
+      Transition::EnterCounting | Transition::WorkHoursStart | Transition::WorkHoursEnd => {
+        self.place_for_phase(ctx);
         true
       }
-      EVENT_BLOCKING_END => {
-        self.state = State::BlockEnd;
-        self.mouse_pos = ctx
-          .pointer_latest_pos()
-          .map(|p| (p.x as u32, p.y as u32))
-          .unwrap_or_default();
+      Transition::BlockingEnd => {
+        self.unlock_anchors.clear();
         false
       }
-      _ => false,
+      Transition::BreakCredited
+      | Transition::BreakWarning
+      | Transition::MicroBreakStart
+      | Transition::MicroBreakEnd
+      | Transition::BreakDeferred => false,
     }
   }
 
-  /// 处理紧急退出逻辑
-  fn handle_escape(&mut self, ctx: &eframe::egui::Context, state: State) {
-    if matches!(state, State::Blocking) {
-      ctx.input(|r| {
-        if r.key_pressed(Key::Escape) {
-          self.escape_pressed_count += 1;
-          if self.escape_pressed_count >= self.escape_count {
-            self
-              .event_signal
-              .store(EVENT_ENTER_COUNTING, std::sync::atomic::Ordering::Relaxed);
-          }
-        }
+  /// 休息结束后鼠标是否移动过，判断解锁。每个窗口第一次出现鼠标时记下位置，
+  /// 之后离开这个位置足够远才算移动；休息结束时鼠标不在的窗口，鼠标移进来就算移动。
+  fn pointer_moved(&mut self, viewport: ViewportId, pos: Option<Pos2>) -> bool {
+    let anchor = self.unlock_anchors.entry(viewport).or_insert(pos);
+    match (*anchor, pos) {
+      (Some(anchor), Some(pos)) => anchor.distance(pos) > UNLOCK_DRIFT,
+      (None, Some(_)) => true,
+      (_, None) => false,
+    }
+  }
+
+  /// 定期把用户空闲时长上报给计时器
+  fn poll_idle(&mut self) {
+    if self.idle_polled_at.elapsed() < IDLE_POLL_INTERVAL {
+      return;
+    }
+    self.idle_polled_at = Instant::now();
+    if let Some(secs) = self.idle_source.idle_secs()
+      && let Some(t) = self.timer.report_idle(secs)
+    {
+      self.transition = Some(t);
+    }
+  }
+
+  /// 定期检测是否需要暂缓休息，并上报给计时器
+  fn poll_busy(&mut self) {
+    if self.busy_polled_at.elapsed() < BUSY_POLL_INTERVAL {
+      return;
+    }
+    self.busy_polled_at = Instant::now();
+    // 只在休息即将开始或正在暂缓时检测，避免频繁扫描进程
+    if !matches!(self.timer.phase(), Phase::Counting)
+      || self.timer.remaining_secs() > BUSY_POLL_AHEAD_SECS
+    {
+      self.timer.report_busy(None);
+      return;
+    }
+    self.busy_process = find_running(self.processes.as_mut(), &self.defer_processes);
+    let reason = if self.timer.config().fullscreen_dnd
+      && self.fullscreen.is_fullscreen(&self.layout.primary)
+    {
+      Some(DeferReason::Fullscreen)
+    } else if self.busy_process.is_some() {
+      Some(DeferReason::Process)
+    } else {
+      None
+    };
+    self.timer.report_busy(reason);
+  }
+
+  /// 完成紧急退出，广播退出方式和原因
+  fn confirm_escape(&mut self, reason: String) {
+    let skipped_secs = self.timer.remaining_secs();
+    if let Some(t) = self.timer.escape() {
+      self.transition = Some(t);
+      let reason = reason.trim();
+      self.publish(EventKind::Escaped {
+        method: self.escape_method,
+        reason: (!reason.is_empty()).then(|| reason.to_string()),
+        skipped_secs,
       });
     }
   }
@@ -213,125 +349,210 @@ impl eframe::App for CounterApp {
 
   fn update(&mut self, ctx: &eframe::egui::Context, _: &mut eframe::Frame) {
     // 必须在函数最开始消费 event
-    if self.handle_event(ctx) {
+    self.handle_commands();
+    let was_paused = self.timer.is_paused();
+    if let Some(t) = self.transition.take().or_else(|| self.timer.tick())
+      && self.apply_transition(ctx, t)
+    {
       ctx.request_repaint();
       return;
     }
+    self.poll_idle();
+    self.poll_busy();
+    self.poll_layout(ctx);
+    if was_paused && !self.timer.is_paused() {
+      // 定时暂停到期自动继续
+      self.publish(EventKind::Resumed);
+    } else {
+      self.bus.update_status(self.status());
+    }
 
-    let is_counting_state = matches!(self.state, State::Counting);
-    let mut panel_frame = Frame::default().fill(Color32::from_rgba_unmultiplied(
-      0,
-      0,
-      0,
-      if is_counting_state { 60 } else { 120 },
-    ));
+    let is_counting_state = matches!(self.timer.phase(), Phase::Counting);
+    let micro_left = self.timer.micro_break_remaining_secs();
+    let warning = self.timer.is_warning();
+    let off_hours = self.timer.is_off_hours();
+    let deferred = self.timer.deferred_by().is_some();
+    let pause_left = self.timer.pause_remaining_secs();
+    let fill = if micro_left.is_some() {
+      // 护眼小休息期间计时小窗闪烁提醒，不锁屏
+      pulse_color(ctx, (30, 140, 80))
+    } else if warning {
+      // 即将休息，计时小窗变色闪烁
+      pulse_color(ctx, (220, 120, 20))
+    } else {
+      Color32::from_rgba_unmultiplied(0, 0, 0, if is_counting_state { 60 } else { 120 })
+    };
+    let mut panel_frame = Frame::default().fill(fill);
     if is_counting_state {
-      panel_frame = panel_frame.corner_radius(COUNTER_WINDOW_RADIUS / self.display.scale);
+      panel_frame = panel_frame.corner_radius(COUNTER_WINDOW_RADIUS / self.layout.primary.scale);
     }
 
-    let state = self.state;
+    let state = self.timer.phase();
     let mut blocking_left_secs: u32 = 0;
+    // 任意一个屏幕上触发的解锁和紧急退出
+    let mut unlock = false;
+    // 休息结束后各个遮挡窗口里的鼠标位置，所有屏幕按同样的规则解锁
+    let mut pointers: Vec<(ViewportId, Option<Pos2>)> = Vec::new();
+    let mut other_escape = EscapeInput::default();
+    let mut escape_confirmed = false;
+    let mut escape_canceled = false;
+    let mut postpone = false;
+    let postpones_left = self.timer.postpones_left();
+    let long_break = self.timer.is_long_break();
     CentralPanel::default().frame(panel_frame).show(ctx, |ui| {
       ui.style_mut().interaction.selectable_labels = false;
 
       match state {
-        State::Counting => {
+        Phase::Counting => {
           let res = ui.interact(ui.max_rect(), Id::new(1), Sense::drag());
           if res.dragged() {
             ctx.send_viewport_cmd(ViewportCommand::StartDrag);
           }
-          let passed_secs =
-            (self.count_paused_time.unwrap_or_else(|| now()) - self.count_start_time) as u32;
+          let passed_secs = self.timer.elapsed_secs();
 
           let minutes = passed_secs / 60;
           let seconds = passed_secs % 60;
 
-          if passed_secs >= self.work_secs {
-            self
-              .event_signal
-              .store(EVENT_ENTER_BLOCKING, std::sync::atomic::Ordering::Relaxed);
+          // 启用长休息时在右上角显示当前是第几轮
+          let every = self.timer.config().long_rest_every;
+          if every > 0 && !off_hours {
+            let scale = self.layout.primary.scale;
+            ui.painter().text(
+              ui.max_rect().right_top() + Vec2::new(-16.0, 8.0) / scale,
+              Align2::RIGHT_TOP,
+              format!("{}/{}", self.timer.cycle(), every),
+              FontId::monospace(COUNTER_WINDOW_FONT_SIZE * 0.3 / scale),
+              Color32::from_white_alpha(160),
+            );
           }
 
-          ui.centered_and_justified(|ui| {
-            ui.label(
-              RichText::new(format!("{:02}:{:02}", minutes, seconds))
-                .monospace()
-                .size(COUNTER_WINDOW_FONT_SIZE / self.display.scale)
-                .color(Color32::WHITE),
+          // 预警和暂缓休息期间可以直接在小窗上推迟
+          if (warning || deferred) && postpones_left > 0 {
+            let scale = self.layout.primary.scale;
+            let rect = eframe::egui::Rect::from_min_size(
+              ui.max_rect().left_top() + Vec2::new(8.0, 4.0) / scale,
+              Vec2::new(40.0, 18.0) / scale,
             );
+            if ui
+              .put(
+                rect,
+                eframe::egui::Button::new(
+                  RichText::new("推迟").size(COUNTER_WINDOW_FONT_SIZE * 0.3 / scale),
+                ),
+              )
+              .clicked()
+            {
+              postpone = true;
+            }
+          }
+
+          ui.centered_and_justified(|ui| {
+            let fsize = COUNTER_WINDOW_FONT_SIZE / self.layout.primary.scale;
+            let text = if off_hours {
+              RichText::new("非工作时间").size(fsize * 0.5)
+            } else if deferred {
+              RichText::new("等待休息").size(fsize * 0.6)
+            } else if let Some(left) = pause_left {
+              RichText::new(format!("暂停 {}", fmt_pause(left))).size(fsize * 0.5)
+            } else {
+              match micro_left {
+                Some(left) => RichText::new(format!("远眺 {}s", left)).size(fsize * 0.7),
+                None => RichText::new(format!("{:02}:{:02}", minutes, seconds))
+                  .monospace()
+                  .size(fsize),
+              }
+            };
+            ui.label(text.color(Color32::WHITE));
           });
         }
-        State::Blocking => {
+        Phase::Blocking => {
           ui.vertical_centered(|ui| {
-            let passed_secs = (now() - self.count_start_time) as u32;
-            let left_secs = self.rest_secs - passed_secs;
+            let left_secs = self.timer.remaining_secs();
             let minutes = left_secs / 60;
             let seconds = left_secs % 60;
-            if left_secs <= 0 {
-              self
-                .event_signal
-                .store(EVENT_BLOCKING_END, std::sync::atomic::Ordering::Relaxed);
-            } else {
-              blocking_left_secs = left_secs;
-            }
-            let screen_height = self.display.screen_size.1;
-            let fsize1 = COUNTER_WINDOW_FONT_SIZE / self.display.scale;
+            blocking_left_secs = left_secs;
+            let screen_height = self.layout.primary.screen_rect.1.1;
+            let fsize1 = COUNTER_WINDOW_FONT_SIZE / self.layout.primary.scale;
             let fsize2 = fsize1 * 0.86;
-            let show_escape = self.escape_pressed_count > (self.escape_count >> 1);
-            ui.add_space(
-              (screen_height - fsize1 - if show_escape { fsize2 + 12.0 } else { 0.0 }) / 2.0,
-            );
+            ui.add_space((screen_height - fsize1) / 2.0 - fsize2);
             ui.label(
-              RichText::new(fmt_count(minutes, seconds))
+              RichText::new(fmt_count(minutes, seconds, long_break))
                 .monospace()
                 .size(fsize1)
                 .color(Color32::WHITE),
             );
-            if show_escape {
+            if postpones_left > 0 {
               ui.add_space(12.0);
+              let text = format!(
+                "推迟 {} 分钟（还可推迟 {} 次）",
+                self.timer.config().postpone_secs.div_ceil(60),
+                postpones_left
+              );
+              if ui.button(RichText::new(text).size(fsize1 * 0.5)).clicked() {
+                postpone = true;
+              }
+            }
+            // 严格模式下不能紧急退出
+            if self.timer.is_strict_break() {
+              return;
+            }
+            ui.add_space(12.0);
+            let input = EscapeInput::read(ctx).merge(self.other_escape);
+            if let Some(reason) = self.escape_reason.as_mut() {
+              // 这里不响应 Escape，连按退出时多按的一下不会丢掉这次退出
               ui.label(
-                RichText::new("即将紧急退出")
-                  .size(fsize2)
-                  .color(Color32::RED),
+                RichText::new("紧急退出原因（可选），回车确认")
+                  .size(fsize2 * 0.6)
+                  .color(Color32::WHITE),
               );
+              if submit_field(ui, reason, fsize2 * 0.6) {
+                escape_confirmed = true;
+              }
+              ui.add_space(12.0);
+              if ui
+                .button(RichText::new("确认退出").size(fsize2 * 0.5))
+                .clicked()
+              {
+                escape_confirmed = true;
+              }
+              if ui
+                .button(RichText::new("取消").size(fsize2 * 0.5))
+                .clicked()
+              {
+                escape_canceled = true;
+              }
+            } else if self.escape.update(ui, input, fsize2) {
+              self.escape_reason = Some(String::new());
             }
           });
         }
-        State::BlockEnd => {
-          let pos = ctx
-            .pointer_latest_pos()
-            .map(|p| (p.x as u32, p.y as u32))
-            .unwrap_or_default();
-
-          if self.mouse_pos.0.abs_diff(pos.0) > 10
-            || self.mouse_pos.1.abs_diff(pos.1) > 10
-            || ui
-              .interact(ui.max_rect(), Id::new(2), Sense::click())
-              .clicked()
+        Phase::BlockEnd => {
+          pointers.push((ctx.viewport_id(), ctx.pointer_latest_pos()));
+          if ui
+            .interact(ui.max_rect(), Id::new(2), Sense::click())
+            .clicked()
           {
-            self
-              .event_signal
-              .store(EVENT_ENTER_COUNTING, std::sync::atomic::Ordering::Relaxed);
+            unlock = true;
           }
           ui.centered_and_justified(|ui| {
             ui.label(
               RichText::new("休息结束，移动鼠标解锁~")
                 .monospace()
-                .size(COUNTER_WINDOW_FONT_SIZE / self.display.scale)
+                .size(COUNTER_WINDOW_FONT_SIZE / self.layout.primary.scale)
                 .color(Color32::WHITE),
             );
           });
         }
       };
 
-      // 渲染第二个屏幕
-      if !matches!(state, State::Counting) {
-        if let Some(sd) = self.second_display.as_ref() {
+      // 其它屏幕各用一个窗口遮挡，倒计时和解锁方式与主屏幕一致
+      if !matches!(state, Phase::Counting) {
+        for bd in self.layout.others.iter() {
           ctx.show_viewport_immediate(
-            ViewportId::from_hash_of("second-blocker"),
+            ViewportId::from_hash_of(("blocker", bd.id)),
             ViewportBuilder::default()
-              .with_position(sd.screen_rect.0)
-              .with_inner_size(sd.screen_rect.1)
+              .with_position(bd.screen_rect.0)
+              .with_inner_size(bd.screen_rect.1)
               .with_transparent(true)
               .with_decorations(false)
               .with_has_shadow(false)
@@ -340,28 +561,30 @@ impl eframe::App for CounterApp {
               .with_always_on_top(),
             |ctx, _class| {
               let panel_frame =
-                Frame::default().fill(Color32::from_rgba_unmultiplied_const(0, 0, 0, 10));
-              let event_signal = self.event_signal.clone();
+                Frame::default().fill(Color32::from_rgba_unmultiplied_const(0, 0, 0, 120));
               CentralPanel::default().frame(panel_frame).show(ctx, |ui| {
                 ui.style_mut().interaction.selectable_labels = false;
-                if blocking_left_secs == 0
-                  && ui
+                other_escape = other_escape.merge(EscapeInput::read(ctx));
+                if matches!(state, Phase::BlockEnd) {
+                  pointers.push((ctx.viewport_id(), ctx.pointer_latest_pos()));
+                  if ui
                     .interact(ui.max_rect(), Id::new(2), Sense::click())
                     .clicked()
-                {
-                  event_signal.store(EVENT_ENTER_COUNTING, std::sync::atomic::Ordering::Relaxed);
+                  {
+                    unlock = true;
+                  }
                 }
                 ui.centered_and_justified(|ui| {
                   ui.label(
                     RichText::new(if blocking_left_secs > 0 {
                       let minutes = blocking_left_secs / 60;
                       let seconds = blocking_left_secs % 60;
-                      fmt_count(minutes, seconds)
+                      fmt_count(minutes, seconds, long_break)
                     } else {
-                      "休息结束，点击鼠标解锁~".to_string()
+                      "休息结束，移动鼠标解锁~".to_string()
                     })
                     .monospace()
-                    .size(COUNTER_WINDOW_FONT_SIZE / sd.scale)
+                    .size(COUNTER_WINDOW_FONT_SIZE / bd.scale)
                     .color(Color32::WHITE),
                   );
                 });
@@ -371,22 +594,47 @@ impl eframe::App for CounterApp {
         }
       }
     });
+    for (viewport, pos) in pointers {
+      unlock |= self.pointer_moved(viewport, pos);
+    }
+    if unlock {
+      self.transition = self.timer.unlock();
+    }
+    if postpone {
+      let kind = self.postpone();
+      self.publish(kind);
+    }
 
     // 处理紧急退出逻辑
-    self.handle_escape(ctx, state);
+    self.other_escape = other_escape;
+    if escape_canceled {
+      self.escape_reason = None;
+      self.escape.reset();
+    }
+    if escape_confirmed {
+      let reason = self.escape_reason.take().unwrap_or_default();
+      self.confirm_escape(reason);
+    }
 
-    if matches!(state, State::Counting | State::Blocking) {
+    if micro_left.is_some() || warning {
+      ctx.request_repaint_after_secs(0.05);
+    } else if matches!(state, Phase::Counting | Phase::Blocking) {
       ctx.request_repaint_after_secs(0.5);
     } else {
       ctx.request_repaint();
     }
   }
 }
-pub fn start_counter_app(settings: &Settings, event_signal: Arc<AtomicU8>) {
-  let counter_app = Box::new(CounterApp::new(settings, event_signal));
+pub fn start_counter_app(
+  settings: &Settings,
+  postponed_today: u32,
+  bus: CounterBusState,
+  commands: Receiver<Command>,
+) {
+  let counter_app = Box::new(CounterApp::new(settings, postponed_today, bus, commands));
 
   std::thread::spawn(move || {
-    let init_rect = counter_app.display.counting_rect;
+    let init_rect = counter_app.layout.primary.counting_rect;
 
     let options = eframe::NativeOptions {
       event_loop_builder: Some(Box::new(|_elb| {
@@ -10,7 +10,7 @@ pub fn open_main_window<R: Runtime>(app: &AppHandle<R>) {
     // let cfg = &app.config().app.windows[0];
     let _ = WebviewWindowBuilder::new(app, "main", tauri::WebviewUrl::App("/index.html".into()))
       .title(APP_TITLE)
-      .inner_size(400.0, 280.0)
+      .inner_size(420.0, 560.0)
       .center()
       .build()
       .unwrap();
@@ -8,29 +8,123 @@ import {
   useForm,
 } from 'jinge-antd';
 import { disable, enable, isEnabled } from '@tauri-apps/plugin-autostart';
+import { vm, vmRaw } from 'jinge';
 
 import { FormItem } from './components/FormItem';
+import { ScheduleEditor } from './components/ScheduleEditor';
+import { Select } from './components/Select';
 import { Switch } from './components/Switch';
+import {
+  type EscapeMethod,
+  EscapeMethodOptions,
+  type Settings,
+} from './service/settings';
 import { globalSettings } from './store/settings';
 import { invoke } from '@tauri-apps/api/core';
+import {
+  type ShortcutAction,
+  type ShortcutConflict,
+  conflictMessages,
+  getShortcutConflicts,
+} from './service/shortcut';
 import z from 'zod';
 
 function App() {
   const { formErrors, validate, control } = useForm(
-    z.object({
-      workMinutes: z.number().int().min(1).max(60),
-      autoStartApp: z.boolean(),
-      restMinutes: z.number().int().min(1).max(30),
-    }),
+    z
+      .object({
+        workMinutes: z.number().int().min(1).max(60),
+        autoStartApp: z.boolean(),
+        restMinutes: z.number().int().min(1).max(30),
+        longRestEvery: z.number().int().min(0).max(10),
+        longRestMinutes: z.number().int().min(1).max(60),
+        microBreakEveryMinutes: z.number().int().min(0).max(60),
+        microBreakSecs: z.number().int().min(5).max(60),
+        warningSecs: z.number().int().min(0).max(300),
+        postponeMinutes: z.number().int().min(1).max(30),
+        postponePerCycle: z.number().int().min(0).max(5),
+        postponePerDay: z.number().int().min(0).max(20),
+        strictMode: z.boolean(),
+        strictLockDuringBreak: z.boolean(),
+        escapeMethod: z.enum(['repeat', 'hold', 'phrase', 'arithmetic']),
+        escapeCount: z.number().int().min(2).max(30),
+        escapeHoldSecs: z.number().int().min(1).max(30),
+        escapePhrase: z.string().trim().min(1).max(50),
+        scheduleEnabled: z.boolean(),
+        scheduleDays: z
+          .array(
+            z
+              .object({
+                startMinute: z.number().int().min(0).max(24 * 60),
+                endMinute: z.number().int().min(0).max(24 * 60),
+              })
+              .refine((w) => w.endMinute > w.startMinute, {
+                message: '结束时间必须晚于开始时间',
+              })
+              .nullable(),
+          )
+          .length(7),
+        fullscreenDnd: z.boolean(),
+        deferMaxMinutes: z.number().int().min(1).max(120),
+        deferProcesses: z.string().max(1000),
+        historyRetentionDays: z.number().int().min(0).max(3650),
+        maxPauseMinutes: z.number().int().min(0).max(7 * 24 * 60),
+        shortcutPause: z.string().max(100),
+        shortcutResume: z.string().max(100),
+        shortcutReset: z.string().max(100),
+        shortcutBreakNow: z.string().max(100),
+      })
+      .refine(
+        (d) => d.longRestEvery === 0 || d.longRestMinutes >= d.restMinutes,
+        {
+          message: '长休息时长不能短于休息时长',
+          path: ['longRestMinutes'],
+        },
+      ),
     {
       defaultValues: {
         autoStartApp: globalSettings.autoStartApp,
         workMinutes: globalSettings.workSecs / 60,
         restMinutes: globalSettings.restSecs / 60,
+        longRestEvery: globalSettings.longRestEvery,
+        longRestMinutes: globalSettings.longRestSecs / 60,
+        microBreakEveryMinutes: globalSettings.microBreakEverySecs / 60,
+        microBreakSecs: globalSettings.microBreakSecs,
+        warningSecs: globalSettings.warningSecs,
+        postponeMinutes: globalSettings.postponeSecs / 60,
+        postponePerCycle: globalSettings.postponePerCycle,
+        postponePerDay: globalSettings.postponePerDay,
+        strictMode: globalSettings.strictMode,
+        strictLockDuringBreak: globalSettings.strictLockDuringBreak,
+        escapeMethod: globalSettings.escapeMethod,
+        escapeCount: globalSettings.escapeCount,
+        escapeHoldSecs: globalSettings.escapeHoldSecs,
+        escapePhrase: globalSettings.escapePhrase,
+        scheduleEnabled: globalSettings.schedule.enabled,
+        scheduleDays: globalSettings.schedule.days,
+        fullscreenDnd: globalSettings.fullscreenDnd,
+        deferMaxMinutes: globalSettings.deferMaxSecs / 60,
+        deferProcesses: globalSettings.deferProcesses.join('\n'),
+        historyRetentionDays: globalSettings.historyRetentionDays,
+        maxPauseMinutes: globalSettings.maxPauseSecs / 60,
+        shortcutPause: globalSettings.shortcuts.pause,
+        shortcutResume: globalSettings.shortcuts.resume,
+        shortcutReset: globalSettings.shortcuts.reset,
+        shortcutBreakNow: globalSettings.shortcuts.breakNow,
       },
     },
   );
   console.log(globalSettings);
+  /** 注册失败的快捷键，显示在对应的输入框下方 */
+  const state = vm<{ conflicts: Partial<Record<ShortcutAction, string>> }>({
+    conflicts: {},
+  });
+  function showConflicts(conflicts: ShortcutConflict[]) {
+    state.conflicts = conflictMessages(conflicts);
+  }
+  getShortcutConflicts()
+    .then(showConflicts)
+    .catch(() => {});
   async function save() {
     const [err, data] = await validate();
     if (err) return;
@@ -51,16 +145,61 @@ function App() {
       }
     }
 
-    const workSecs = data.workMinutes * 60;
-    const restSecs = data.restMinutes * 60;
-    if (
-      workSecs !== globalSettings.workSecs ||
-      restSecs !== globalSettings.restSecs
-    ) {
-      globalSettings.workSecs = workSecs;
-      globalSettings.restSecs = restSecs;
-      await invoke('tauri_refresh_settings');
-      message.success('保存成功！');
+    const settings: Settings = {
+      workSecs: data.workMinutes * 60,
+      restSecs: data.restMinutes * 60,
+      longRestSecs: data.longRestMinutes * 60,
+      longRestEvery: data.longRestEvery,
+      microBreakEverySecs: data.microBreakEveryMinutes * 60,
+      microBreakSecs: data.microBreakSecs,
+      warningSecs: data.warningSecs,
+      postponeSecs: data.postponeMinutes * 60,
+      postponePerCycle: data.postponePerCycle,
+      postponePerDay: data.postponePerDay,
+      strictMode: data.strictMode,
+      strictLockDuringBreak: data.strictLockDuringBreak,
+      escapeMethod: data.escapeMethod,
+      escapeCount: data.escapeCount,
+      escapeHoldSecs: data.escapeHoldSecs,
+      escapePhrase: data.escapePhrase,
+      schedule: { enabled: data.scheduleEnabled, days: data.scheduleDays },
+      fullscreenDnd: data.fullscreenDnd,
+      deferMaxSecs: data.deferMaxMinutes * 60,
+      deferProcesses: data.deferProcesses
+        .split('\n')
+        .map((name) => name.trim())
+        .filter((name) => !!name),
+      historyRetentionDays: data.historyRetentionDays,
+      maxPauseSecs: data.maxPauseMinutes * 60,
+      shortcuts: {
+        pause: data.shortcutPause.trim(),
+        resume: data.shortcutResume.trim(),
+        reset: data.shortcutReset.trim(),
+        breakNow: data.shortcutBreakNow.trim(),
+      },
+      autoStartApp: data.autoStartApp,
+    };
+    // 逐项比较，字段的顺序不影响结果
+    const current = vmRaw(globalSettings);
+    const changed = (Object.keys(settings) as (keyof Settings)[]).some(
+      (key) => JSON.stringify(settings[key]) !== JSON.stringify(current[key]),
+    );
+    if (!changed) return;
+    try {
+      // 先由后端校验，通过后才写入 store，失败时可以修改后重新保存
+      const conflicts = await invoke<ShortcutConflict[]>(
+        'tauri_refresh_settings',
+        { settings: { ...current, ...settings } },
+      );
+      Object.assign(globalSettings, settings);
+      showConflicts(conflicts);
+      if (conflicts.length) {
+        message.error('其它配置已生效，但有快捷键注册失败');
+      } else {
+        message.success('保存成功！');
+      }
+    } catch (ex) {
+      message.error(`应用配置失败：${ex as string}`);
     }
   }
 
@@ -110,6 +249,455 @@ function App() {
             )}
           </Controller>
         </FormItem>
+        <FormItem label="长休息间隔：" error={formErrors.longRestEvery}>
+          <Controller control={control} name="longRestEvery">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={1}
+                  min={0}
+                  max={10}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  轮（0 表示不启用）
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="长休息时长：" error={formErrors.longRestMinutes}>
+          <Controller control={control} name="longRestMinutes">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={1}
+                  min={1}
+                  max={60}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  分钟
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="休息预警：" error={formErrors.warningSecs}>
+          <Controller control={control} name="warningSecs">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={10}
+                  min={0}
+                  max={300}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  秒（0 表示不预警）
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="护眼提醒：" error={formErrors.microBreakEveryMinutes}>
+          <Controller control={control} name="microBreakEveryMinutes">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={1}
+                  min={0}
+                  max={60}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  分钟（0 表示不启用）
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="远眺时长：" error={formErrors.microBreakSecs}>
+          <Controller control={control} name="microBreakSecs">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={1}
+                  min={5}
+                  max={60}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  秒
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="推迟时长：" error={formErrors.postponeMinutes}>
+          <Controller control={control} name="postponeMinutes">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={1}
+                  min={1}
+                  max={30}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  分钟
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="每轮可推迟：" error={formErrors.postponePerCycle}>
+          <Controller control={control} name="postponePerCycle">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={1}
+                  min={0}
+                  max={5}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  次（0 表示不允许推迟）
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="每天可推迟：" error={formErrors.postponePerDay}>
+          <Controller control={control} name="postponePerDay">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={1}
+                  min={0}
+                  max={20}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  次
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="严格模式：" error={formErrors.strictMode}>
+          <Controller control={control} name="strictMode">
+            {(field) => (
+              <div className="flex items-center">
+                <Switch
+                  value={field.value}
+                  on:change={(checked) => {
+                    field['on:change'](checked);
+                  }}
+                />
+              </div>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="休息中锁定：" error={formErrors.strictLockDuringBreak}>
+          <Controller control={control} name="strictLockDuringBreak">
+            {(field) => (
+              <div className="flex items-center">
+                <Switch
+                  value={field.value}
+                  on:change={(checked) => {
+                    field['on:change'](checked);
+                  }}
+                />
+              </div>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="紧急退出：" error={formErrors.escapeMethod}>
+          <Controller control={control} name="escapeMethod">
+            {(field) => (
+              <Select
+                value={field.value}
+                options={EscapeMethodOptions}
+                on:change={(v) => {
+                  field['on:change'](v as EscapeMethod);
+                }}
+              />
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="连按次数：" error={formErrors.escapeCount}>
+          <Controller control={control} name="escapeCount">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={1}
+                  min={2}
+                  max={30}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  次 Escape
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="按住时长：" error={formErrors.escapeHoldSecs}>
+          <Controller control={control} name="escapeHoldSecs">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={1}
+                  min={1}
+                  max={30}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  秒 Escape
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="确认语句：" error={formErrors.escapePhrase}>
+          <Controller control={control} name="escapePhrase">
+            {(field) => (
+              <input
+                className="h-8 rounded-md border border-black/15 px-2 outline-none focus:border-primary"
+                value={field.value}
+                on:input={(evt: Event) => {
+                  field['on:change']((evt.target as HTMLInputElement).value);
+                }}
+              />
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="工作时间表：" error={formErrors.scheduleEnabled}>
+          <Controller control={control} name="scheduleEnabled">
+            {(field) => (
+              <div className="flex items-center">
+                <Switch
+                  value={field.value}
+                  on:change={(checked) => {
+                    field['on:change'](checked);
+                  }}
+                />
+              </div>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="工作时间：" error={formErrors.scheduleDays}>
+          <Controller control={control} name="scheduleDays">
+            {(field) => (
+              <ScheduleEditor
+                value={field.value}
+                on:change={(v) => {
+                  field['on:change'](v);
+                }}
+              />
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="全屏免打扰：" error={formErrors.fullscreenDnd}>
+          <Controller control={control} name="fullscreenDnd">
+            {(field) => (
+              <div className="flex items-center">
+                <Switch
+                  value={field.value}
+                  on:change={(checked) => {
+                    field['on:change'](checked);
+                  }}
+                />
+              </div>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="最长暂缓：" error={formErrors.deferMaxMinutes}>
+          <Controller control={control} name="deferMaxMinutes">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={5}
+                  min={1}
+                  max={120}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  分钟
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="暂缓程序：" error={formErrors.deferProcesses}>
+          <Controller control={control} name="deferProcesses">
+            {(field) => (
+              <textarea
+                className="h-20 rounded-md border border-black/15 px-2 py-1 outline-none focus:border-primary"
+                placeholder="每行一个程序名，例如 zoom"
+                value={field.value}
+                on:input={(evt: Event) => {
+                  field['on:change']((evt.target as HTMLTextAreaElement).value);
+                }}
+              />
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="历史保留：" error={formErrors.historyRetentionDays}>
+          <Controller control={control} name="historyRetentionDays">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={30}
+                  min={0}
+                  max={3650}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  天（0 表示永久保留）
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem label="最长暂停：" error={formErrors.maxPauseMinutes}>
+          <Controller control={control} name="maxPauseMinutes">
+            {(field) => (
+              <InputWrapper>
+                <InputNumber
+                  noRoundedR
+                  step={30}
+                  min={0}
+                  max={7 * 24 * 60}
+                  value={field.value}
+                  on:change={(v) => {
+                    field['on:change'](v);
+                  }}
+                />
+                <InputAddon className="shrink-0 whitespace-nowrap px-2">
+                  分钟（0 表示不限制）
+                </InputAddon>
+              </InputWrapper>
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem
+          label="暂停快捷键："
+          error={formErrors.shortcutPause ?? state.conflicts.pause}
+        >
+          <Controller control={control} name="shortcutPause">
+            {(field) => (
+              <input
+                className="h-8 rounded-md border border-black/15 px-2 outline-none focus:border-primary"
+                placeholder="例如 CommandOrControl+Alt+P，留空表示不使用"
+                value={field.value}
+                on:input={(evt: Event) => {
+                  field['on:change']((evt.target as HTMLInputElement).value);
+                }}
+              />
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem
+          label="继续快捷键："
+          error={formErrors.shortcutResume ?? state.conflicts.resume}
+        >
+          <Controller control={control} name="shortcutResume">
+            {(field) => (
+              <input
+                className="h-8 rounded-md border border-black/15 px-2 outline-none focus:border-primary"
+                placeholder="例如 CommandOrControl+Alt+O，留空表示不使用"
+                value={field.value}
+                on:input={(evt: Event) => {
+                  field['on:change']((evt.target as HTMLInputElement).value);
+                }}
+              />
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem
+          label="重置快捷键："
+          error={formErrors.shortcutReset ?? state.conflicts.reset}
+        >
+          <Controller control={control} name="shortcutReset">
+            {(field) => (
+              <input
+                className="h-8 rounded-md border border-black/15 px-2 outline-none focus:border-primary"
+                placeholder="例如 CommandOrControl+Alt+R，留空表示不使用"
+                value={field.value}
+                on:input={(evt: Event) => {
+                  field['on:change']((evt.target as HTMLInputElement).value);
+                }}
+              />
+            )}
+          </Controller>
+        </FormItem>
+        <FormItem
+          label="立即休息快捷键："
+          error={formErrors.shortcutBreakNow ?? state.conflicts.breakNow}
+        >
+          <Controller control={control} name="shortcutBreakNow">
+            {(field) => (
+              <input
+                className="h-8 rounded-md border border-black/15 px-2 outline-none focus:border-primary"
+                placeholder="例如 CommandOrControl+Alt+B，留空表示不使用"
+                value={field.value}
+                on:input={(evt: Event) => {
+                  field['on:change']((evt.target as HTMLInputElement).value);
+                }}
+              />
+            )}
+          </Controller>
+        </FormItem>
         <FormItem label="开机启动：" error={formErrors.autoStartApp}>
           <Controller control={control} name="autoStartApp">
             {(field) => (
@@ -0,0 +1,89 @@
+import { type WithEvents, vm } from 'jinge';
+
+import type { WorkWindow } from '@/service/settings';
+
+const WEEKDAYS = ['周一', '周二', '周三', '周四', '周五', '周六', '周日'];
+/** 新勾选某一天时使用的默认时间段 */
+const DEFAULT_WINDOW: WorkWindow = { startMinute: 9 * 60, endMinute: 18 * 60 + 30 };
+
+function toTime(minute: number) {
+  const h = Math.floor(minute / 60);
+  const m = minute % 60;
+  return `${String(h).padStart(2, '0')}:${String(m).padStart(2, '0')}`;
+}
+
+function fromTime(value: string) {
+  const [h, m] = value.split(':').map(Number);
+  return (h || 0) * 60 + (m || 0);
+}
+
+/**
+ * 编辑周一到周日的工作时间段，每天最多一个时间段，不勾选表示当天不工作
+ */
+export function ScheduleEditor(
+  props: {
+    value: (WorkWindow | null)[];
+  } & WithEvents<{
+    change?: (value: (WorkWindow | null)[]) => void;
+  }>,
+) {
+  const state = vm({
+    days: props.value.map((d) => (d ? { ...d } : null)),
+  });
+
+  function update(index: number, day: WorkWindow | null) {
+    state.days[index] = day;
+    props['on:change']?.(state.days.map((d) => (d ? { ...d } : null)));
+  }
+
+  return (
+    <div className="flex flex-col gap-1">
+      {WEEKDAYS.map((label, index) => (
+        <div className="flex items-center gap-2">
+          <label className="flex w-16 items-center gap-1">
+            <input
+              type="checkbox"
+              checked={!!state.days[index]}
+              on:change={(evt: Event) => {
+                const checked = (evt.target as HTMLInputElement).checked;
+                update(index, checked ? { ...DEFAULT_WINDOW } : null);
+              }}
+            />
+            {label}
+          </label>
+          {state.days[index] && (
+            <>
+              <input
+                type="time"
+                className="h-7 rounded-md border border-black/15 px-1"
+                value={toTime(state.days[index].startMinute)}
+                on:change={(evt: Event) => {
+                  const day = state.days[index];
+                  if (!day) return;
+                  update(index, {
+                    ...day,
+                    startMinute: fromTime((evt.target as HTMLInputElement).value),
+                  });
+                }}
+              />
+              <span>-</span>
+              <input
+                type="time"
+                className="h-7 rounded-md border border-black/15 px-1"
+                value={toTime(state.days[index].endMinute)}
+                on:change={(evt: Event) => {
+                  const day = state.days[index];
+                  if (!day) return;
+                  update(index, {
+                    ...day,
+                    endMinute: fromTime((evt.target as HTMLInputElement).value),
+                  });
+                }}
+              />
+            </>
+          )}
+        </div>
+      ))}
+    </div>
+  );
+}
@@ -0,0 +1,29 @@
+import { type WithEvents, cx } from 'jinge';
+
+export function Select(
+  props: {
+    className?: string;
+    value?: string;
+    options: { value: string; label: string }[];
+  } & WithEvents<{
+    change?: (value: string) => void;
+  }>,
+) {
+  return (
+    <select
+      className={cx(
+        'h-8 rounded-md border border-black/15 bg-white px-2 outline-none focus:border-primary',
+        props.className,
+      )}
+      on:change={(evt: Event) => {
+        props['on:change']?.((evt.target as HTMLSelectElement).value);
+      }}
+    >
+      {props.options.map((opt) => (
+        <option value={opt.value} selected={opt.value === props.value}>
+          {opt.label}
+        </option>
+      ))}
+    </select>
+  );
+}
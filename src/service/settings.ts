@@ -1,10 +1,97 @@
+/** 休息期间紧急退出的方式 */
+export type EscapeMethod = 'repeat' | 'hold' | 'phrase' | 'arithmetic';
+export const EscapeMethodOptions: { value: EscapeMethod; label: string }[] = [
+  { value: 'repeat', label: '连按 Escape' },
+  { value: 'hold', label: '按住 Escape' },
+  { value: 'phrase', label: '输入确认语句' },
+  { value: 'arithmetic', label: '答对算术题' },
+];
+
+/** 一天中的工作时间段，用从零点开始的分钟数表示 */
+export interface WorkWindow {
+  startMinute: number;
+  endMinute: number;
+}
+export interface Schedule {
+  /** 未启用时全天都是工作时间 */
+  enabled: boolean;
+  /** 周一到周日的工作时间段，null 表示当天不工作 */
+  days: (WorkWindow | null)[];
+}
+
+/** 全局快捷键，例如 CommandOrControl+Alt+P，空字符串表示不绑定 */
+export interface ShortcutSettings {
+  pause: string;
+  resume: string;
+  reset: string;
+  breakNow: string;
+}
+
 export interface Settings {
   workSecs: number;
   restSecs: number;
+  longRestSecs: number;
+  /** 每隔多少轮工作进行一次长休息，0 表示不启用 */
+  longRestEvery: number;
+  /** 护眼小休息的间隔，0 表示不启用 */
+  microBreakEverySecs: number;
+  microBreakSecs: number;
+  /** 休息开始前多少秒发出预警，0 表示不预警 */
+  warningSecs: number;
+  postponeSecs: number;
+  /** 每轮工作最多推迟几次，0 表示不允许推迟 */
+  postponePerCycle: number;
+  postponePerDay: number;
+  /** 严格模式：休息期间不能紧急退出、推迟，托盘的暂停/重置/退出不可用 */
+  strictMode: boolean;
+  /** 休息期间不允许关闭严格模式 */
+  strictLockDuringBreak: boolean;
+  escapeMethod: EscapeMethod;
+  escapeCount: number;
+  escapeHoldSecs: number;
+  escapePhrase: string;
+  schedule: Schedule;
+  /** 主屏幕有全屏窗口（演示、视频会议）时暂缓休息 */
+  fullscreenDnd: boolean;
+  /** 暂缓休息的最长秒数，超过后无论如何都开始休息 */
+  deferMaxSecs: number;
+  /** 这些程序运行时暂缓休息，例如 zoom、obs */
+  deferProcesses: string[];
+  /** 历史记录保留的天数，0 表示永久保留 */
+  historyRetentionDays: number;
+  /** 暂停的最长秒数，到点自动继续，0 表示不限制 */
+  maxPauseSecs: number;
+  shortcuts: ShortcutSettings;
   autoStartApp: boolean;
 }
 export const DefaultSettings: Settings = {
   workSecs: 0,
   restSecs: 0,
+  longRestSecs: 15 * 60,
+  longRestEvery: 0,
+  microBreakEverySecs: 0,
+  microBreakSecs: 20,
+  warningSecs: 60,
+  postponeSecs: 5 * 60,
+  postponePerCycle: 1,
+  postponePerDay: 3,
+  strictMode: false,
+  strictLockDuringBreak: false,
+  escapeMethod: 'repeat',
+  escapeCount: 10,
+  escapeHoldSecs: 5,
+  escapePhrase: '我确认现在必须跳过休息',
+  schedule: {
+    enabled: false,
+    days: [0, 1, 2, 3, 4, 5, 6].map((i) =>
+      i < 5 ? { startMinute: 9 * 60, endMinute: 18 * 60 + 30 } : null,
+    ),
+  },
+  fullscreenDnd: false,
+  deferMaxSecs: 15 * 60,
+  deferProcesses: [],
+  historyRetentionDays: 365,
+  maxPauseSecs: 0,
+  shortcuts: { pause: '', resume: '', reset: '', breakNow: '' },
   autoStartApp: false,
 };
@@ -0,0 +1,24 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export type ShortcutAction = 'pause' | 'resume' | 'reset' | 'breakNow';
+
+/** 注册失败的快捷键，通常是被其它程序占用了 */
+export interface ShortcutConflict {
+  action: ShortcutAction;
+  shortcut: string;
+  message: string;
+}
+
+/** 按操作整理的冲突提示，用于显示在对应的快捷键下方 */
+export function conflictMessages(conflicts: ShortcutConflict[]) {
+  const messages: Partial<Record<ShortcutAction, string>> = {};
+  for (const c of conflicts) {
+    messages[c.action] = `${c.shortcut} 注册失败，可能已被其它程序占用（${c.message}）`;
+  }
+  return messages;
+}
+
+/** 最近一次注册快捷键时的冲突 */
+export function getShortcutConflicts() {
+  return invoke<ShortcutConflict[]>('tauri_get_shortcut_conflicts');
+}